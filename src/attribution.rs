@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use anyhow::Error;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::github_api::{GitHubAPI, GitHubAPIAuditLogEvent};
+
+/// Audit log actions that set the value of a secret.
+const SECRET_ACTIONS: [&str; 6] = [
+    "repo.create_actions_secret",
+    "repo.update_actions_secret",
+    "environment.create_actions_secret",
+    "environment.update_actions_secret",
+    "org.create_actions_secret",
+    "org.update_actions_secret",
+];
+
+/// Pages of 100 events read per action, newest first.
+const MAX_PAGES: u32 = 5;
+
+/// Audit log events are written shortly after the secret changed. An event further
+/// away from `updated_at` belongs to an earlier change.
+const MAX_DELAY_SECONDS: i64 = 300;
+
+/// Who last created or updated a secret.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretUpdate {
+    pub actor: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Where a secret is stored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SecretScope {
+    Repository(String),
+    Environment(String, String),
+    Organization,
+}
+
+/// Latest update of each secret of the organization, read from the audit log.
+#[derive(Debug, Default)]
+pub struct SecretUpdates {
+    latest: HashMap<(SecretScope, String), SecretUpdate>,
+}
+
+impl SecretUpdates {
+    /// Requires an organization owner token with the `read:audit_log` scope.
+    pub async fn fetch(github_api: &GitHubAPI, organization: &str) -> Result<Self, Error> {
+        let mut events = Vec::new();
+        for action in SECRET_ACTIONS {
+            for page in 1..=MAX_PAGES {
+                let page_events = github_api.get_audit_log(organization, &format!("action:{}", action), page).await?;
+                let last_page = page_events.len() < 100;
+                events.extend(page_events);
+                if last_page {
+                    break;
+                }
+            }
+        }
+        Ok(SecretUpdates::from_events(events))
+    }
+
+    pub fn from_events(events: impl IntoIterator<Item = GitHubAPIAuditLogEvent>) -> Self {
+        let mut updates = SecretUpdates::default();
+        for event in events {
+            let (Some(actor), Some(name)) = (event.actor.clone(), event.secret_name()) else {
+                continue;
+            };
+            let scope = match (event.action.split('.').next(), event.repo.clone(), event.environment_name.clone()) {
+                (Some("org"), _, _) => SecretScope::Organization,
+                (Some("environment"), Some(repo), Some(environment)) => SecretScope::Environment(repo, environment),
+                (Some("repo"), Some(repo), _) => SecretScope::Repository(repo),
+                _ => continue,
+            };
+            let Some(timestamp) = DateTime::from_timestamp_millis(event.timestamp) else {
+                continue;
+            };
+            let update = SecretUpdate { actor, timestamp };
+            updates.latest
+                .entry((scope, name.to_uppercase()))
+                .and_modify(|latest| if latest.timestamp < update.timestamp { *latest = update.clone() })
+                .or_insert(update);
+        }
+        updates
+    }
+
+    /// The update that set the current value of the secret, which was last updated at
+    /// `updated_at`. `None` if the audit log has no event for it, e.g. because it is
+    /// older than the retention period.
+    pub fn attribute(&self, scope: SecretScope, name: &str, updated_at: DateTime<Utc>) -> Option<SecretUpdate> {
+        let update = self.latest.get(&(scope, name.to_uppercase()))?;
+        let delay = (update.timestamp - updated_at).abs();
+        (delay <= Duration::seconds(MAX_DELAY_SECONDS)).then(|| update.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(action: &str, actor: &str, timestamp: &str, environment: Option<&str>) -> GitHubAPIAuditLogEvent {
+        serde_json::from_value(serde_json::json!({
+            "@timestamp": DateTime::parse_from_rfc3339(timestamp).unwrap().timestamp_millis(),
+            "action": action,
+            "actor": actor,
+            "repo": "my_org/my_repo",
+            "environment_name": environment,
+            "secret_name": "npm_token",
+        })).unwrap()
+    }
+
+    fn updated_at(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_attribute() {
+        let updates = SecretUpdates::from_events(vec![
+            event("repo.create_actions_secret", "alice", "2024-01-01T00:00:01Z", None),
+            event("repo.update_actions_secret", "bob", "2024-03-01T12:00:02Z", None),
+            event("environment.update_actions_secret", "carol", "2024-04-01T00:00:00Z", Some("production")),
+        ]);
+        let repository = || SecretScope::Repository("my_org/my_repo".to_string());
+
+        let update = updates.attribute(repository(), "NPM_TOKEN", updated_at("2024-03-01T12:00:00Z")).unwrap();
+        assert_eq!(update.actor, "bob");
+        assert_eq!(
+            updates.attribute(
+                SecretScope::Environment("my_org/my_repo".to_string(), "production".to_string()),
+                "NPM_TOKEN",
+                updated_at("2024-04-01T00:00:00Z")
+            ).unwrap().actor,
+            "carol"
+        );
+        // The change that set the current value is not in the audit log.
+        assert_eq!(updates.attribute(repository(), "NPM_TOKEN", updated_at("2024-05-01T00:00:00Z")), None);
+        assert_eq!(updates.attribute(SecretScope::Organization, "NPM_TOKEN", updated_at("2024-03-01T12:00:00Z")), None);
+    }
+}
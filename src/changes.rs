@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::prelude::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::history::{HistoryRecord, Snapshot};
+use crate::validator::ValidatorState;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretChange {
+    pub repository: String,
+    pub secret: String,
+    pub state: ValidatorState,
+    pub updated_at: DateTime<Utc>,
+    pub previous_updated_at: Option<DateTime<Utc>>,
+}
+
+impl SecretChange {
+    fn new(record: &HistoryRecord, previous: Option<&HistoryRecord>) -> Self {
+        SecretChange {
+            repository: record.repository.clone(),
+            secret: record.secret.clone(),
+            state: record.state,
+            updated_at: record.updated_at,
+            previous_updated_at: previous.map(|p| p.updated_at),
+        }
+    }
+}
+
+/// Differences between the previous and the current run.
+#[derive(Debug, Default, Serialize)]
+pub struct ChangeReport {
+    pub rotated: Vec<SecretChange>,
+    pub created: Vec<SecretChange>,
+    pub deleted: Vec<SecretChange>,
+    pub newly_expired: Vec<SecretChange>,
+}
+
+impl ChangeReport {
+    /// Compares two snapshots. Secrets are only reported as created or deleted if
+    /// their repository was scanned in both runs, as a repository that failed or
+    /// was filtered out would otherwise look like it lost or gained all secrets.
+    pub fn new(previous: &Snapshot, current: &Snapshot) -> Self {
+        let key = |record: &HistoryRecord| (record.repository.clone(), record.secret.clone());
+        let previous_by_key: HashMap<_, _> = previous.records.iter().map(|r| (key(r), r)).collect();
+        let current_keys: HashSet<_> = current.records.iter().map(key).collect();
+        let scanned_in_both = |repository: &str| {
+            previous.scanned_repositories.contains(repository) && current.scanned_repositories.contains(repository)
+        };
+
+        let mut report = ChangeReport::default();
+        for record in current.records.iter() {
+            match previous_by_key.get(&key(record)) {
+                None if scanned_in_both(&record.repository) => report.created.push(SecretChange::new(record, None)),
+                None => {},
+                Some(prev) => {
+                    if record.updated_at != prev.updated_at {
+                        report.rotated.push(SecretChange::new(record, Some(prev)));
+                    }
+                    if record.state == ValidatorState::Expired && prev.state != ValidatorState::Expired {
+                        report.newly_expired.push(SecretChange::new(record, Some(prev)));
+                    }
+                }
+            }
+        }
+        for record in previous.records.iter() {
+            if scanned_in_both(&record.repository) && !current_keys.contains(&key(record)) {
+                report.deleted.push(SecretChange::new(record, Some(record)));
+            }
+        }
+        report
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rotated.is_empty()
+            && self.created.is_empty()
+            && self.deleted.is_empty()
+            && self.newly_expired.is_empty()
+    }
+
+    /// The most severe state in the report, used to pick the log level or annotation.
+    pub fn state(&self) -> ValidatorState {
+        if self.newly_expired.is_empty() {
+            ValidatorState::NotExpired
+        } else {
+            ValidatorState::Expired
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn record(repository: &str, secret: &str, updated_at: DateTime<Utc>, state: ValidatorState) -> HistoryRecord {
+        HistoryRecord {
+            scanned_at: Utc::now(),
+            repository: repository.to_string(),
+            secret: secret.to_string(),
+            created_at: updated_at,
+            updated_at,
+            state,
+            days_age: 0,
+            days_left: 0,
+            days_overdue: 0,
+        }
+    }
+
+    fn snapshot(records: Vec<HistoryRecord>, scanned_repositories: &[&str]) -> Snapshot {
+        Snapshot {
+            records,
+            scanned_repositories: scanned_repositories.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_change_report() {
+        let old = Utc::now() - Duration::days(100);
+        let new = Utc::now();
+        let previous = vec![
+            record("org/repo", "ROTATED", old, ValidatorState::Expired),
+            record("org/repo", "DELETED", old, ValidatorState::Expired),
+            record("org/repo", "EXPIRING", old, ValidatorState::ExpiresSoon),
+            record("org/other", "NOT_SCANNED", old, ValidatorState::Expired),
+        ];
+        let current = vec![
+            record("org/repo", "ROTATED", new, ValidatorState::NotExpired),
+            record("org/repo", "EXPIRING", old, ValidatorState::Expired),
+            record("org/repo", "CREATED", new, ValidatorState::NotExpired),
+        ];
+        let previous = snapshot(previous, &["org/repo", "org/other"]);
+        let current = snapshot(current, &["org/repo"]);
+        let report = ChangeReport::new(&previous, &current);
+        assert_eq!(report.rotated.len(), 1);
+        assert_eq!(report.rotated[0].secret, "ROTATED");
+        assert_eq!(report.rotated[0].previous_updated_at, Some(old));
+        assert_eq!(report.created.len(), 1);
+        assert_eq!(report.created[0].secret, "CREATED");
+        assert_eq!(report.deleted.len(), 1);
+        assert_eq!(report.deleted[0].secret, "DELETED");
+        assert_eq!(report.newly_expired.len(), 1);
+        assert_eq!(report.newly_expired[0].secret, "EXPIRING");
+        assert_eq!(report.state(), ValidatorState::Expired);
+    }
+
+    #[test]
+    fn test_no_changes() {
+        let now = Utc::now();
+        let records = snapshot(vec![record("org/repo", "SECRET", now, ValidatorState::NotExpired)], &["org/repo"]);
+        assert!(ChangeReport::new(&records, &records).is_empty());
+    }
+
+    #[test]
+    fn test_last_secret_deleted() {
+        let now = Utc::now();
+        let previous = snapshot(vec![record("org/repo", "SECRET", now, ValidatorState::NotExpired)], &["org/repo"]);
+        let current = snapshot(Vec::new(), &["org/repo"]);
+        let report = ChangeReport::new(&previous, &current);
+        assert_eq!(report.deleted.len(), 1);
+        assert_eq!(report.deleted[0].secret, "SECRET");
+    }
+
+    #[test]
+    fn test_repository_not_scanned_in_previous_run() {
+        let now = Utc::now();
+        // org/failed failed in the previous run, its secrets are not new.
+        let previous = snapshot(vec![record("org/repo", "SECRET", now, ValidatorState::NotExpired)], &["org/repo"]);
+        let current = snapshot(vec![
+            record("org/repo", "SECRET", now, ValidatorState::NotExpired),
+            record("org/failed", "SECRET", now, ValidatorState::NotExpired),
+        ], &["org/repo", "org/failed"]);
+        assert!(ChangeReport::new(&previous, &current).is_empty());
+    }
+}
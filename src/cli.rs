@@ -1,16 +1,99 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
 use tracing::Level;
 
 use crate::config::NotifierType;
 
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  All secrets are compliant
+  1  Secrets expire soon, but none are expired
+  2  Expired secrets were found
+  3  The configuration or the arguments are invalid
+  4  The scan failed or repositories were skipped
+  5  Audit findings with severity High or Critical were found, but no expired secrets";
+
 #[derive(Parser, Debug)]
+#[command(after_help = EXIT_CODES)]
 pub struct Cli {
-    #[arg(short, long)]
+    /// Configuration file in TOML, YAML or JSON. Can be repeated, later files override earlier ones
+    #[arg(short, long = "config", global = true)]
+    pub config: Vec<PathBuf>,
+    #[arg(short, long, global = true)]
     pub organization: Option<String>,
-    #[arg(short, long, default_value_t = default_log_level())]
+    /// Repository to scan instead of the organization. Can be repeated
+    #[arg(short, long = "repo", value_name = "OWNER/NAME", global = true)]
+    pub repositories: Vec<String>,
+    #[arg(short, long, global = true, default_value_t = default_log_level())]
     pub log_level: Level,
-    #[arg(short, long, value_enum)]
+    #[arg(short, long, global = true, value_enum)]
     pub notifier_type: Option<NotifierType>,
+    /// Command to run, defaults to `scan`
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Validate all secrets of the organization and send notifications
+    Scan,
+    /// List repositories or secrets without validating them
+    #[command(subcommand)]
+    List(ListCommand),
+    /// Show how the state of a secret was determined
+    Explain {
+        /// Repository in the format owner/name
+        repository: String,
+        /// Name of the secret
+        secret: String,
+    },
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Query the scan history
+    #[command(subcommand)]
+    History(HistoryCommand),
+    /// Run the observer continuously on a schedule
+    Serve {
+        /// Cron expression with seconds, e.g. "0 0 8 * * *"
+        #[arg(long, conflicts_with = "interval_minutes")]
+        schedule: Option<String>,
+        /// Minutes between two runs
+        #[arg(long)]
+        interval_minutes: Option<u64>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ListCommand {
+    /// List the repositories of the organization
+    Repos,
+    /// List the secrets of all repositories of the organization
+    Secrets,
+    /// List the webhooks of all repositories and the organization
+    Webhooks,
+    /// List fine-grained personal access tokens and SAML authorized credentials of the organization
+    Credentials,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Validate and print the effective configuration with credentials redacted
+    Check,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistoryCommand {
+    /// Show all recorded findings of a secret
+    Secret {
+        /// Repository in the format owner/name
+        repository: String,
+        /// Name of the secret
+        secret: String,
+    },
+    /// Show the compliance of every recorded run
+    Trend,
 }
 
 /// Provides default log level as tracing::Level::INFO
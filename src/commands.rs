@@ -0,0 +1,224 @@
+use anyhow::Error;
+use tracing::warn;
+
+use crate::attribution::{SecretScope, SecretUpdates};
+use crate::audit::{credential_authorizations, personal_access_tokens};
+use crate::cli::{HistoryCommand, ListCommand};
+use crate::config::{Configuration, InvalidConfiguration};
+use crate::github_api::{GitHubAPI, GitHubAPIRepository};
+use crate::history::History;
+use crate::validator::{Exemption, RotationPolicy, Validator, ValidatorState};
+use crate::workflows::{fetch_workflow_files, SecretReferences};
+
+/// The configured repositories, or all repositories of the organization.
+async fn repositories(config: &Configuration, github_api: &GitHubAPI) -> Result<Vec<GitHubAPIRepository>, Error> {
+    if let Some(names) = config.github.repositories() {
+        let mut repositories = Vec::new();
+        for name in names {
+            repositories.push(github_api.get_repository(name).await?);
+        }
+        return Ok(repositories);
+    }
+    let organization = config.github.organization.as_deref()
+        .ok_or_else(|| InvalidConfiguration("No organization provided.".to_string()))?;
+    github_api.get_repositories(organization).await
+}
+
+/// Prints the repositories or secrets to scan without validating them.
+pub async fn list(config: &Configuration, command: ListCommand) -> Result<(), Error> {
+    let github_api = GitHubAPI::new(None, config.github.api_token())?;
+    match command {
+        ListCommand::Repos => {
+            for repository in repositories(config, &github_api).await? {
+                println!("{}", repository.full_name);
+            }
+        },
+        ListCommand::Secrets => {
+            println!("{:<40} {:<40} {:<25} {:<25}", "REPOSITORY", "SECRET", "CREATED AT", "UPDATED AT");
+            let mut failed = 0;
+            for repository in repositories(config, &github_api).await? {
+                let secrets = match github_api.get_secrets(&repository).await {
+                    Ok(secrets) => secrets.secrets,
+                    Err(e) => {
+                        warn!("Failed to list secrets of {}. Reason: {:#}", repository.full_name, e);
+                        failed += 1;
+                        continue;
+                    }
+                };
+                for secret in secrets {
+                    println!(
+                        "{:<40} {:<40} {:<25} {:<25}",
+                        repository.full_name,
+                        secret.name,
+                        secret.created_at.format("%Y-%m-%d %H:%M:%S"),
+                        secret.updated_at.format("%Y-%m-%d %H:%M:%S")
+                    );
+                }
+            }
+            if failed > 0 {
+                return Err(anyhow::anyhow!("The secrets of {} repositories could not be listed.", failed));
+            }
+        },
+        ListCommand::Webhooks => {
+            println!("{:<40} {:<10} {:<35} {:<8} {:<8} {:<12} {:<12}", "TARGET", "ID", "HOST", "ACTIVE", "SECRET", "INSECURE SSL", "UPDATED AT");
+            let mut webhooks = Vec::new();
+            if let Some(organization) = config.github.organization.as_deref() {
+                webhooks.push((organization.to_string(), github_api.get_organization_webhooks(organization).await?));
+            }
+            let mut failed = 0;
+            for repository in repositories(config, &github_api).await? {
+                match github_api.get_webhooks(&repository.full_name).await {
+                    Ok(repository_webhooks) => webhooks.push((repository.full_name, repository_webhooks)),
+                    Err(e) => {
+                        warn!("Failed to list webhooks of {}. Reason: {:#}", repository.full_name, e);
+                        failed += 1;
+                    }
+                }
+            }
+            for (target, target_webhooks) in webhooks {
+                let Some(target_webhooks) = target_webhooks else {
+                    warn!("The webhooks of {} can't be read without admin access.", target);
+                    continue;
+                };
+                for webhook in target_webhooks {
+                    println!(
+                        "{:<40} {:<10} {:<35} {:<8} {:<8} {:<12} {:<12}",
+                        target,
+                        webhook.id,
+                        webhook.host(),
+                        webhook.active,
+                        webhook.has_secret(),
+                        webhook.is_insecure_ssl(),
+                        webhook.updated_at.format("%Y-%m-%d")
+                    );
+                }
+            }
+            if failed > 0 {
+                return Err(anyhow::anyhow!("The webhooks of {} repositories could not be listed.", failed));
+            }
+        },
+        ListCommand::Credentials => {
+            let organization = config.github.organization.as_deref()
+                .ok_or_else(|| InvalidConfiguration("No organization provided.".to_string()))?;
+            let mut credentials = personal_access_tokens(&github_api, organization).await?;
+            credentials.extend(credential_authorizations(&github_api, organization).await?);
+            let date = |date: Option<chrono::DateTime<chrono::Utc>>, empty: &str| {
+                date.map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_else(|| empty.to_string())
+            };
+            println!("{:<25} {:<35} {:<30} {:<12} {:<12} {:<12}", "OWNER", "TYPE", "NAME", "GRANTED AT", "EXPIRES AT", "LAST USED");
+            for credential in credentials {
+                println!(
+                    "{:<25} {:<35} {:<30} {:<12} {:<12} {:<12}",
+                    credential.owner,
+                    credential.kind,
+                    credential.name,
+                    credential.granted_at.format("%Y-%m-%d"),
+                    date(credential.expires_at, "never"),
+                    date(credential.last_used, "-")
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the policy and exemption applied to a secret and why it reached its state.
+pub async fn explain(config: &Configuration, repository: &str, name: &str) -> Result<ValidatorState, Error> {
+    let github_api = GitHubAPI::new(None, config.github.api_token())?;
+    let mut secret = github_api.get_secret(repository, name).await?;
+    if config.observer.check_workflows {
+        let references = SecretReferences::parse(&fetch_workflow_files(&github_api, repository).await?);
+        secret.workflows = references.workflows(&secret.name);
+        secret.exposure = references.exposure(&secret.name);
+    }
+    let mut explanation = Validator::new(config.observer.clone()).explain_secret(&secret).await?;
+    if config.observer.attribute_updates {
+        // Like the scan, the secret is explained without attribution if the audit log can't be read.
+        let organization = repository.split('/').next().unwrap_or_default();
+        match SecretUpdates::fetch(&github_api, organization).await {
+            Ok(updates) => explanation.result.updated_by = updates.attribute(
+                SecretScope::Repository(repository.to_string()), &secret.name, secret.updated_at
+            ),
+            Err(e) => warn!("Failed to read the audit log of {}, the update is not attributed. Reason: {:#}", organization, e),
+        }
+    }
+    let result = &explanation.result;
+
+    println!("Secret:     {}/{}", repository, secret.name);
+    println!("Updated at: {} ({} days ago)", secret.updated_at.format("%Y-%m-%d %H:%M:%S"), result.days_age);
+    if config.observer.attribute_updates {
+        println!("Updated by: {}", result.owner().unwrap_or("unknown, no matching audit log event"));
+    }
+    match explanation.policy {
+        RotationPolicy::Default(days) => println!("Policy:     rotate every {} days (observer.default_rotation_days)", days),
+        RotationPolicy::Suffix(days) => println!("Policy:     rotate every {} days (suffix _R{} of the secret name)", days, days),
+    }
+    match &explanation.exemption {
+        Some(Exemption::IgnoreList) => println!("Exemption:  listed in observer.ignore_secrets"),
+        Some(Exemption::IgnorePattern(pattern)) => println!("Exemption:  matches observer.ignore_pattern '{}'", pattern),
+        None => println!("Exemption:  none"),
+    }
+    let reason = match result.state {
+        ValidatorState::Ignored => "the secret is exempt from validation".to_string(),
+        ValidatorState::Expired => format!(
+            "the secret is {} days old, {} days past its rotation period of {} days",
+            result.days_age, result.days_overdue, explanation.policy.days()
+        ),
+        ValidatorState::ExpiresSoon => format!(
+            "{} days left, within the notice period of {} days (observer.expiration_notice_days)",
+            result.days_left, explanation.notice_days
+        ),
+        ValidatorState::NotExpired => format!(
+            "{} days left, more than the notice period of {} days (observer.expiration_notice_days)",
+            result.days_left, explanation.notice_days
+        ),
+    };
+    println!("State:      {:?}, {}", result.state, reason);
+    if config.observer.check_workflows {
+        println!("Workflows:  {}", if secret.workflows.is_empty() { "none".to_string() } else { secret.workflows.join(", ") });
+        println!("Exposure:   {:?}", result.exposure);
+    }
+    println!("Severity:   {:?}", result.severity);
+    Ok(result.state)
+}
+
+/// Prints the effective configuration with all credentials redacted.
+pub fn check_config(config: &Configuration) -> Result<(), Error> {
+    println!("{}", serde_json::to_string_pretty(&config.redacted())?);
+    Ok(())
+}
+
+pub fn print_history(history: &History, command: HistoryCommand) -> Result<(), Error> {
+    match command {
+        HistoryCommand::Secret { repository, secret } => {
+            println!("{:<25} {:<12} {:<25} {:>8} {:>9} {:>12}", "SCANNED AT", "STATE", "UPDATED AT", "AGE", "DAYS LEFT", "DAYS OVERDUE");
+            for record in history.secret_history(&repository, &secret)? {
+                println!(
+                    "{:<25} {:<12} {:<25} {:>8} {:>9} {:>12}",
+                    record.scanned_at.format("%Y-%m-%d %H:%M:%S"),
+                    format!("{:?}", record.state),
+                    record.updated_at.format("%Y-%m-%d %H:%M:%S"),
+                    record.days_age,
+                    record.days_left,
+                    record.days_overdue
+                );
+            }
+        },
+        HistoryCommand::Trend => {
+            println!("{:<25} {:>6} {:>8} {:>12} {:>11} {:>8} {:>11}", "SCANNED AT", "TOTAL", "EXPIRED", "EXPIRES SOON", "NOT EXPIRED", "IGNORED", "COMPLIANCE");
+            for entry in history.compliance_trend()? {
+                println!(
+                    "{:<25} {:>6} {:>8} {:>12} {:>11} {:>8} {:>10.1}%",
+                    entry.scanned_at.format("%Y-%m-%d %H:%M:%S"),
+                    entry.total,
+                    entry.expired,
+                    entry.expires_soon,
+                    entry.not_expired,
+                    entry.ignored,
+                    entry.compliance()
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,61 +1,608 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::Command;
+use std::str::FromStr;
+
+use anyhow::{Context, Error};
+use config::builder::{ConfigBuilder, DefaultState};
 use config::{Config, ConfigError, Environment, File};
-use serde::Deserialize;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::notifier::{InvalidTemplate, MessageTemplates};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 #[allow(unused)]
 pub struct GitHubConfig {
     pub organization: Option<String>,
-    pub token: String,
+    /// Repositories in the format owner/name, scanned instead of the organization.
+    pub repositories: Option<Vec<String>>,
+    pub token: Option<Token>,
+    /// File that contains the token, e.g. a mounted Kubernetes secret.
+    pub token_file: Option<String>,
+    /// Shell command that prints the token, e.g. `gh auth token`.
+    pub token_command: Option<String>,
+}
+
+/// Access token that is never printed, neither in `Debug` output nor when serialized.
+#[derive(Clone, Deserialize)]
+#[serde(transparent)]
+pub struct Token(String);
+
+impl Token {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Token({})", REDACTED)
+    }
+}
+
+impl Serialize for Token {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl GitHubConfig {
+    /// Reads the token from `token_file` or `token_command` unless it is set directly.
+    pub fn resolve_token(&mut self) -> Result<(), Error> {
+        if self.token.is_some() {
+            return Ok(());
+        }
+        let token = if let Some(file) = &self.token_file {
+            std::fs::read_to_string(file)
+                .with_context(|| format!("Failed to read github.token_file {}", file))?
+        } else if let Some(command) = &self.token_command {
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .output()
+                .with_context(|| format!("Failed to run github.token_command '{}'", command))?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "github.token_command '{}' failed with {}: {}",
+                    command, output.status, String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            String::from_utf8(output.stdout).context("github.token_command printed an invalid token")?
+        } else {
+            return Ok(());
+        };
+        let token = token.trim();
+        if token.is_empty() {
+            anyhow::bail!("The token read from github.token_file or github.token_command is empty");
+        }
+        self.token = Some(Token(token.to_string()));
+        Ok(())
+    }
+
+    /// Explicitly configured repositories, if any.
+    pub fn repositories(&self) -> Option<&[String]> {
+        self.repositories.as_deref().filter(|repositories| !repositories.is_empty())
+    }
+
+    /// The resolved token as expected by the GitHub API client.
+    pub fn api_token(&self) -> Option<String> {
+        self.token.as_ref().map(|token| token.expose().to_string())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 #[allow(unused)]
 pub struct ObserverConfig {
     pub default_rotation_days: i64,
     pub expiration_notice_days: i64,
     pub ignore_pattern: Option<String>,
     pub ignore_secrets: Option<Vec<String>>,
+    pub fail_on_expires_soon: bool,
+    /// Exit with the scan failure code if any repository could not be scanned.
+    pub fail_on_skipped_repositories: bool,
+    pub max_failed_repositories: Option<usize>,
+    /// Detect unused and missing secrets by reading the workflows of each repository.
+    #[serde(default)]
+    pub check_workflows: bool,
+    /// Read who last set each secret from the organization audit log.
+    #[serde(default)]
+    pub attribute_updates: bool,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[allow(unused)]
 pub struct NotifierConfig {
     pub notifier_type: NotifierType,
-    pub slack_webhook: Option<String>
+    pub slack_webhook: Option<String>,
+    pub issue_repository: Option<String>,
+    #[serde(default)]
+    pub issue_grouping: IssueGrouping,
+    pub issue_label: Option<String>,
+    pub issue_assignees: Option<Vec<String>>,
+    pub alert_overdue_days: i64,
+    pub pagerduty_routing_key: Option<String>,
+    pub opsgenie_api_key: Option<String>,
+    pub opsgenie_url: Option<String>,
+    #[serde(default)]
+    pub deduplicate: bool,
+    pub state_file: Option<String>,
+    pub reminder_expired_days: i64,
+    pub reminder_expires_soon_days: i64,
+    /// Message templates keyed by notifier name, e.g. `slack` or `pagerduty`.
+    pub templates: Option<HashMap<String, TemplateConfig>>,
 }
 
-#[derive(Debug, Clone, Deserialize, clap::ValueEnum, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[allow(unused)]
+pub struct TemplateConfig {
+    pub message: Option<String>,
+    pub digest: Option<String>,
+    pub changes: Option<String>,
+    pub failures: Option<String>,
+    pub usage: Option<String>,
+    pub findings: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueGrouping {
+    #[default]
+    Repository,
+    Secret,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, clap::ValueEnum, Default)]
 #[allow(unused)]
 #[serde(rename_all = "lowercase")]
 pub enum NotifierType {
     Slack,
     #[clap(name = "github")]
     GitHub,
+    Issues,
     #[default]
     Log,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[allow(unused)]
+pub struct HistoryConfig {
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[allow(unused)]
+pub struct DaemonConfig {
+    pub schedule: Option<String>,
+    pub interval_minutes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[allow(unused)]
+pub struct MetricsConfig {
+    pub listen: Option<String>,
+    pub textfile: Option<String>,
+}
+
+/// Audits of the repository and organization configuration, reported as findings.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[allow(unused)]
+pub struct AuditConfig {
+    /// Report environments that hold secrets without protection rules.
+    #[serde(default)]
+    pub environments: bool,
+    /// Report organization secrets that are shared with all, public or archived repositories.
+    #[serde(default)]
+    pub organization_secrets: bool,
+    /// Report repository, environment and organization variables that contain credentials.
+    #[serde(default)]
+    pub variables: bool,
+    /// Validate deploy keys against the rotation policy.
+    #[serde(default)]
+    pub deploy_keys: bool,
+    /// Report fine-grained personal access tokens without expiry or unused.
+    #[serde(default)]
+    pub personal_access_tokens: bool,
+    /// Report SAML authorized credentials without expiry or unused.
+    #[serde(default)]
+    pub credential_authorizations: bool,
+    /// Report webhooks without secret, with SSL verification disabled or an old configuration.
+    #[serde(default)]
+    pub webhooks: bool,
+    /// Days after which a credential that was not used is reported.
+    #[serde(default)]
+    pub unused_credential_days: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[allow(unused)]
 pub struct Configuration {
+    #[serde(default)]
     pub github: GitHubConfig,
     pub observer: ObserverConfig,
     pub notifier: NotifierConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+}
+
+/// Marks an error that is caused by missing or invalid configuration.
+#[derive(Debug)]
+pub struct InvalidConfiguration(pub String);
+
+impl fmt::Display for InvalidConfiguration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidConfiguration {}
+
+const REDACTED: &str = "<redacted>";
+
+fn is_repository_name(name: &str) -> bool {
+    name.split('/').filter(|part| !part.is_empty()).count() == 2
+}
+
+/// Sections of the configuration that can be set from the environment.
+const SECTIONS: [&str; 7] = ["github", "observer", "notifier", "history", "daemon", "metrics", "audit"];
+
+/// Reads `GHSO_<SECTION>_<KEY>` variables. Only the first `_` after the section
+/// separates, so keys with underscores like `GHSO_GITHUB_TOKEN_FILE` map to
+/// `github.token_file`.
+fn environment(vars: impl IntoIterator<Item = (String, String)>) -> Environment {
+    let source = vars.into_iter()
+        .filter_map(|(name, value)| {
+            let (section, key) = name.strip_prefix("GHSO_")?.split_once('_')?;
+            SECTIONS.contains(&section.to_lowercase().as_str())
+                .then(|| (format!("GHSO_{}__{}", section, key), value))
+        })
+        .collect();
+    Environment::with_prefix("GHSO")
+        .prefix_separator("_")
+        .separator("__")
+        .try_parsing(true)
+        .source(Some(source))
 }
 
+/// Names of the notifiers that accept templates.
+const TEMPLATE_NOTIFIERS: [&str; 6] = ["log", "slack", "github", "issues", "pagerduty", "opsgenie"];
+
 impl Configuration {
-    pub fn new() -> Result<Self, ConfigError> {
-        let config = Config::builder()
-            .add_source(File::with_name("config").required(false))
-            .add_source(
-                Environment::with_prefix("GHSO")
-                .separator("_")
-                .try_parsing(true)
-            )
+    /// Loads the given files in order, later files override earlier ones. Without
+    /// files, an optional `config.{toml,yaml,json}` in the working directory is used.
+    /// Environment variables override all files.
+    pub fn new(files: &[PathBuf]) -> Result<Self, ConfigError> {
+        let mut builder = Config::builder();
+        if files.is_empty() {
+            builder = builder.add_source(File::with_name("config").required(false));
+        }
+        for file in files {
+            builder = builder.add_source(File::from(file.as_path()));
+        }
+        Configuration::build(builder.add_source(environment(std::env::vars())))
+    }
+
+    fn build(builder: ConfigBuilder<DefaultState>) -> Result<Self, ConfigError> {
+        let config = builder
             .set_default("observer.default_rotation_days", 90)?
             .set_default("observer.expiration_notice_days", 14)?
+            .set_default("observer.fail_on_expires_soon", true)?
+            .set_default("observer.fail_on_skipped_repositories", true)?
             .set_default("notifier.notifier_type", "log")?
+            .set_default("notifier.alert_overdue_days", 30)?
+            .set_default("notifier.reminder_expired_days", 1)?
+            .set_default("notifier.reminder_expires_soon_days", 7)?
+            .set_default("audit.unused_credential_days", 90)?
             .build()?;
         config.try_deserialize()
     }
+
+    /// Inside GitHub Actions, scans the current repository with `GITHUB_TOKEN` unless
+    /// an organization, repositories or a token are configured.
+    pub fn apply_github_actions(&mut self, var: impl Fn(&str) -> Option<String>) {
+        if var("GITHUB_ACTIONS").as_deref() != Some("true") {
+            return;
+        }
+        let github = &mut self.github;
+        if github.organization.is_none() && github.repositories().is_none() {
+            if let Some(repository) = var("GITHUB_REPOSITORY") {
+                info!("Running in GitHub Actions, scanning {}.", repository);
+                github.repositories = Some(vec![repository]);
+            }
+        }
+        if github.token.is_none() && github.token_file.is_none() && github.token_command.is_none() {
+            github.token = var("GITHUB_TOKEN").map(Token);
+        }
+    }
+
+    /// Copy of the configuration with all credentials replaced, safe to print.
+    /// The token is redacted by `Token` itself.
+    pub fn redacted(&self) -> Self {
+        let redact = |value: &Option<String>| value.as_ref().map(|_| REDACTED.to_string());
+        let mut config = self.clone();
+        config.notifier.slack_webhook = redact(&config.notifier.slack_webhook);
+        config.notifier.pagerduty_routing_key = redact(&config.notifier.pagerduty_routing_key);
+        config.notifier.opsgenie_api_key = redact(&config.notifier.opsgenie_api_key);
+        config
+    }
+
+    /// Checks the whole configuration and reports all problems at once, each
+    /// prefixed with its key path.
+    pub fn validate(&self) -> Result<(), InvalidConfiguration> {
+        let mut problems = Vec::new();
+        let mut problem = |key: &str, message: String| problems.push(format!("{}: {}", key, message));
+
+        let github = &self.github;
+        let token_sources = [github.token.is_some(), github.token_file.is_some(), github.token_command.is_some()];
+        match token_sources.iter().filter(|set| **set).count() {
+            0 => problem("github.token", "is required, or set github.token_file or github.token_command".to_string()),
+            1 => {},
+            _ => problem("github.token", "only one of github.token, github.token_file and github.token_command can be set".to_string()),
+        }
+        if github.token.as_ref().is_some_and(|token| token.expose().trim().is_empty()) {
+            problem("github.token", "must not be empty".to_string());
+        }
+        for repository in github.repositories.iter().flatten() {
+            if !is_repository_name(repository) {
+                problem("github.repositories", format!("must have the format owner/name, got '{}'", repository));
+            }
+        }
+
+        let observer = &self.observer;
+        if observer.default_rotation_days <= 0 {
+            problem("observer.default_rotation_days", format!("must be greater than 0, got {}", observer.default_rotation_days));
+        }
+        if observer.expiration_notice_days < 0 {
+            problem("observer.expiration_notice_days", format!("must not be negative, got {}", observer.expiration_notice_days));
+        } else if observer.default_rotation_days > 0 && observer.expiration_notice_days >= observer.default_rotation_days {
+            problem("observer.expiration_notice_days", format!(
+                "must be less than observer.default_rotation_days ({}), got {}",
+                observer.default_rotation_days, observer.expiration_notice_days
+            ));
+        }
+        if let Some(pattern) = &observer.ignore_pattern {
+            if let Err(e) = Regex::new(pattern) {
+                problem("observer.ignore_pattern", format!("invalid regex: {}", e));
+            }
+        }
+
+        let notifier = &self.notifier;
+        match notifier.notifier_type {
+            NotifierType::Slack if notifier.slack_webhook.is_none() => {
+                problem("notifier.slack_webhook", "is required for the slack notifier".to_string());
+            },
+            _ => {},
+        }
+        if let Some(webhook) = &notifier.slack_webhook {
+            if !webhook.starts_with("https://") {
+                problem("notifier.slack_webhook", "must be an https:// URL".to_string());
+            }
+        }
+        if let Some(repository) = &notifier.issue_repository {
+            if !is_repository_name(repository) {
+                problem("notifier.issue_repository", format!("must have the format owner/name, got '{}'", repository));
+            }
+        }
+        if notifier.alert_overdue_days < 0 {
+            problem("notifier.alert_overdue_days", format!("must not be negative, got {}", notifier.alert_overdue_days));
+        }
+        if let Some(url) = &notifier.opsgenie_url {
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                problem("notifier.opsgenie_url", format!("must be an http(s) URL, got '{}'", url));
+            }
+        }
+        if notifier.reminder_expired_days < 0 {
+            problem("notifier.reminder_expired_days", format!("must not be negative, got {}", notifier.reminder_expired_days));
+        }
+        if notifier.reminder_expires_soon_days < 0 {
+            problem("notifier.reminder_expires_soon_days", format!("must not be negative, got {}", notifier.reminder_expires_soon_days));
+        }
+        if let Some(templates) = &notifier.templates {
+            let mut names: Vec<_> = templates.keys().collect();
+            names.sort();
+            for name in names {
+                if !TEMPLATE_NOTIFIERS.contains(&name.as_str()) {
+                    problem(&format!("notifier.templates.{}", name), format!("unknown notifier, expected one of {}", TEMPLATE_NOTIFIERS.join(", ")));
+                }
+                if let Err(e) = MessageTemplates::new(templates.get(name)) {
+                    match e.downcast_ref::<InvalidTemplate>() {
+                        Some(invalid) => problem(&format!("notifier.templates.{}.{}", name, invalid.key), format!("{:#}", e)),
+                        None => problem(&format!("notifier.templates.{}", name), format!("{:#}", e)),
+                    }
+                }
+            }
+        }
+
+        if let Some(schedule) = &self.daemon.schedule {
+            if self.daemon.interval_minutes.is_some() {
+                problem("daemon.schedule", "can't be combined with daemon.interval_minutes".to_string());
+            }
+            if let Err(e) = cron::Schedule::from_str(schedule) {
+                problem("daemon.schedule", format!("invalid cron expression '{}': {}", schedule, e));
+            }
+        }
+        if self.daemon.interval_minutes == Some(0) {
+            problem("daemon.interval_minutes", "must be greater than 0".to_string());
+        }
+        if let Some(listen) = &self.metrics.listen {
+            if let Err(e) = SocketAddr::from_str(listen) {
+                problem("metrics.listen", format!("invalid address '{}': {}", listen, e));
+            }
+        }
+        if (self.audit.personal_access_tokens || self.audit.credential_authorizations) && self.audit.unused_credential_days <= 0 {
+            problem("audit.unused_credential_days", format!("must be greater than 0, got {}", self.audit.unused_credential_days));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidConfiguration(format!("{} problem(s) found:\n  {}", problems.len(), problems.join("\n  "))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn configuration() -> Configuration {
+        serde_json::from_value(json!({
+            "github": { "organization": "org", "token": "token" },
+            "observer": { "default_rotation_days": 90, "expiration_notice_days": 14, "fail_on_expires_soon": true, "fail_on_skipped_repositories": true },
+            "notifier": {
+                "notifier_type": "log",
+                "alert_overdue_days": 30,
+                "reminder_expired_days": 1,
+                "reminder_expires_soon_days": 7
+            }
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(configuration().validate().is_ok());
+
+        let mut config = configuration();
+        config.observer.default_rotation_days = 0;
+        config.observer.ignore_pattern = Some("(".to_string());
+        config.notifier.notifier_type = NotifierType::Slack;
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.starts_with("3 problem(s) found:"));
+        assert!(message.contains("observer.default_rotation_days: must be greater than 0, got 0"));
+        assert!(message.contains("observer.ignore_pattern: invalid regex"));
+        assert!(message.contains("notifier.slack_webhook: is required for the slack notifier"));
+    }
+
+    #[test]
+    fn test_validate_templates() {
+        let mut config = configuration();
+        let template = TemplateConfig {
+            message: Some("{{name}}".to_string()),
+            digest: None,
+            changes: None,
+            failures: Some("{{#each repositories}}".to_string()),
+            usage: None,
+            findings: None,
+        };
+        config.notifier.templates = Some(HashMap::from([("slack".to_string(), template)]));
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.starts_with("1 problem(s) found:"));
+        assert!(message.contains("notifier.templates.slack.failures: Invalid failures template"));
+    }
+
+    #[test]
+    fn test_validate_notice_days() {
+        let mut config = configuration();
+        config.observer.expiration_notice_days = 90;
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("observer.expiration_notice_days: must be less than observer.default_rotation_days (90), got 90"));
+    }
+
+    #[test]
+    fn test_redacted() {
+        let mut config = configuration();
+        config.notifier.pagerduty_routing_key = Some("key".to_string());
+        let redacted = config.redacted();
+        assert_eq!(format!("{:?}", redacted.github.token), "Some(Token(<redacted>))");
+        assert_eq!(serde_json::to_value(&config).unwrap()["github"]["token"], REDACTED);
+        assert_eq!(redacted.notifier.pagerduty_routing_key.as_deref(), Some(REDACTED));
+        assert_eq!(redacted.notifier.opsgenie_api_key, None);
+    }
+
+    #[test]
+    fn test_resolve_token() {
+        let mut config = configuration();
+        config.github.token = None;
+        config.github.token_command = Some("echo ' command-token '".to_string());
+        assert!(config.validate().is_ok());
+        config.github.resolve_token().unwrap();
+        assert_eq!(config.github.api_token().as_deref(), Some("command-token"));
+
+        let mut config = configuration();
+        config.github.token_file = Some("token.txt".to_string());
+        assert!(config.validate().unwrap_err().to_string().contains("github.token: only one of"));
+
+        config.github.token = None;
+        config.github.token_command = Some("exit 1".to_string());
+        config.github.token_file = None;
+        assert!(config.github.resolve_token().is_err());
+    }
+
+    #[test]
+    fn test_example_configuration() {
+        let builder = Config::builder().add_source(File::new("config.toml.example", config::FileFormat::Toml));
+        let config = Configuration::build(builder).unwrap();
+        assert_eq!(config.github.organization.as_deref(), Some("my_org"));
+        assert_eq!(config.observer.default_rotation_days, 180);
+        assert!(matches!(config.notifier.notifier_type, NotifierType::Slack));
+        assert!(config.notifier.slack_webhook.is_some());
+    }
+
+    #[test]
+    fn test_merge_files() {
+        let dir = std::env::temp_dir().join(format!("ghso-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let yaml = dir.join("base.yaml");
+        std::fs::write(&yaml, "github:\n  organization: base\n  token: token\nobserver:\n  default_rotation_days: 30\n").unwrap();
+        let json = dir.join("override.json");
+        std::fs::write(&json, r#"{"github": {"organization": "override"}}"#).unwrap();
+
+        let builder = Config::builder()
+            .add_source(File::from(yaml.as_path()))
+            .add_source(File::from(json.as_path()));
+        let config = Configuration::build(builder).unwrap();
+        assert_eq!(config.github.organization.as_deref(), Some("override"));
+        assert_eq!(config.github.api_token().as_deref(), Some("token"));
+        assert_eq!(config.observer.default_rotation_days, 30);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_environment() {
+        let vars = [
+            ("GHSO_GITHUB_ORGANIZATION", "my_org"),
+            ("GHSO_GITHUB_TOKEN_FILE", "/run/secrets/github-token"),
+            ("GHSO_OBSERVER_DEFAULT_ROTATION_DAYS", "30"),
+            ("GHSO_NOTIFIER_NOTIFIER_TYPE", "slack"),
+            ("GHSO_UNKNOWN_KEY", "ignored"),
+            ("HOME", "/root"),
+        ].map(|(name, value)| (name.to_string(), value.to_string()));
+        let config = Configuration::build(Config::builder().add_source(environment(vars))).unwrap();
+        assert_eq!(config.github.organization.as_deref(), Some("my_org"));
+        assert_eq!(config.github.token_file.as_deref(), Some("/run/secrets/github-token"));
+        assert_eq!(config.observer.default_rotation_days, 30);
+        assert!(matches!(config.notifier.notifier_type, NotifierType::Slack));
+    }
+
+    #[test]
+    fn test_apply_github_actions() {
+        let env = |name: &str| match name {
+            "GITHUB_ACTIONS" => Some("true".to_string()),
+            "GITHUB_REPOSITORY" => Some("org/current".to_string()),
+            "GITHUB_TOKEN" => Some("actions-token".to_string()),
+            _ => None,
+        };
+        let mut config = configuration();
+        config.github.organization = None;
+        config.github.token = None;
+        config.apply_github_actions(env);
+        assert_eq!(config.github.repositories(), Some(&["org/current".to_string()][..]));
+        assert_eq!(config.github.api_token().as_deref(), Some("actions-token"));
+
+        // Configured values take precedence.
+        let mut config = configuration();
+        config.apply_github_actions(env);
+        assert_eq!(config.github.repositories(), None);
+        assert_eq!(config.github.api_token().as_deref(), Some("token"));
+    }
 }
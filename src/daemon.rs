@@ -0,0 +1,149 @@
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Error};
+use chrono::prelude::{DateTime, Utc};
+use tokio::sync::watch;
+use tracing::{error, info};
+
+use crate::config::DaemonConfig;
+use crate::metrics::metrics_server;
+use crate::observer::Observer;
+
+/// When the daemon runs the observer.
+pub enum Schedule {
+    Cron(Box<cron::Schedule>),
+    Interval(Duration),
+}
+
+impl Schedule {
+    pub fn from_config(config: &DaemonConfig) -> Result<Self, Error> {
+        match (&config.schedule, config.interval_minutes) {
+            (Some(_), Some(_)) => Err(anyhow::anyhow!("Either daemon.schedule or daemon.interval_minutes can be set, not both.")),
+            (Some(expression), None) => {
+                let schedule = cron::Schedule::from_str(expression)
+                    .with_context(|| format!("Invalid cron expression '{}'", expression))?;
+                Ok(Schedule::Cron(Box::new(schedule)))
+            },
+            (None, Some(0)) => Err(anyhow::anyhow!("daemon.interval_minutes must be greater than 0.")),
+            (None, Some(minutes)) => Ok(Schedule::Interval(Duration::from_secs(minutes * 60))),
+            (None, None) => Err(anyhow::anyhow!("No schedule provided. Please set daemon.schedule or daemon.interval_minutes.")),
+        }
+    }
+
+    /// Time to wait until the next run. Interval schedules start right away.
+    fn next_delay(&self, now: DateTime<Utc>, first_run: bool) -> Option<Duration> {
+        match self {
+            Schedule::Cron(schedule) => {
+                let next = schedule.after(&now).next()?;
+                (next - now).to_std().ok()
+            },
+            Schedule::Interval(_) if first_run => Some(Duration::ZERO),
+            Schedule::Interval(interval) => Some(*interval),
+        }
+    }
+}
+
+/// Sends `true` once the process receives SIGTERM or Ctrl-C.
+pub fn shutdown_signal() -> watch::Receiver<bool> {
+    let (sender, receiver) = watch::channel(false);
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        info!("Shutdown requested.");
+        let _ = sender.send(true);
+    });
+    receiver
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut terminate = signal(SignalKind::terminate()).expect("failed to install SIGTERM handler");
+    tokio::select! {
+        _ = terminate.recv() => {},
+        _ = tokio::signal::ctrl_c() => {},
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Runs the observer on the schedule until a shutdown is requested. The observer,
+/// and with it the notification state, is kept between runs.
+pub async fn serve(mut observer: Observer, schedule: Schedule, metrics_address: Option<SocketAddr>) -> Result<(), Error> {
+    let mut shutdown = shutdown_signal();
+    observer.set_shutdown_signal(shutdown.clone());
+
+    let metrics_server = match metrics_address {
+        Some(address) => Some(tokio::spawn(metrics_server(address, observer.metrics(), shutdown.clone())?)),
+        None => None,
+    };
+
+    let mut first_run = true;
+    loop {
+        let Some(delay) = schedule.next_delay(Utc::now(), first_run) else {
+            info!("Schedule has no upcoming runs.");
+            return Ok(());
+        };
+        first_run = false;
+        info!("Next run in {} seconds.", delay.as_secs());
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {},
+            _ = shutdown.changed() => {},
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+
+        match observer.run().await {
+            Ok(_) => info!("Observer finished successfully."),
+            Err(e) => error!("Observer failed. Reason: {:?}", e),
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+    }
+    if let Some(metrics_server) = metrics_server {
+        metrics_server.await??;
+    }
+    info!("Daemon stopped.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_from_config() {
+        let config = DaemonConfig { schedule: Some("0 0 8 * * *".to_string()), interval_minutes: None };
+        assert!(matches!(Schedule::from_config(&config), Ok(Schedule::Cron(_))));
+
+        let config = DaemonConfig { schedule: Some("invalid".to_string()), interval_minutes: None };
+        assert!(Schedule::from_config(&config).is_err());
+
+        let config = DaemonConfig { schedule: None, interval_minutes: Some(0) };
+        assert!(Schedule::from_config(&config).is_err());
+
+        let config = DaemonConfig { schedule: None, interval_minutes: None };
+        assert!(Schedule::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_next_delay() {
+        let interval = Schedule::Interval(Duration::from_secs(60));
+        let now = Utc::now();
+        assert_eq!(interval.next_delay(now, true), Some(Duration::ZERO));
+        assert_eq!(interval.next_delay(now, false), Some(Duration::from_secs(60)));
+
+        let cron = Schedule::from_config(&DaemonConfig {
+            schedule: Some("0 * * * * *".to_string()),
+            interval_minutes: None,
+        }).unwrap();
+        let delay = cron.next_delay(now, true).unwrap();
+        assert!(delay <= Duration::from_secs(60));
+    }
+}
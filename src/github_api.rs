@@ -1,27 +1,72 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
 use anyhow::Error;
-use reqwest::{Client, Response};
+use base64::Engine;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode, Url};
 use chrono::prelude::{Utc, DateTime};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use tracing::{debug, error};
+use tracing::debug;
+
+use crate::config::InvalidConfiguration;
+use crate::workflows::Exposure;
+
+/// The variables endpoints return at most 30 variables per page.
+const VARIABLES_PER_PAGE: usize = 30;
 
+/// Request statistics shared by all clones of a `GitHubAPI`.
+#[derive(Default)]
+struct GitHubAPIStats {
+    requests: AtomicU64,
+    rate_limit_remaining: AtomicI64,
+}
 
+#[derive(Clone)]
 pub struct GitHubAPI {
     url: Option<String>,    
     token: Option<String>,
     client: Client,
+    stats: Arc<GitHubAPIStats>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GitHubAPIError {
     message: String,
-    documentation_url: String,
+    #[serde(default)]
+    documentation_url: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GitHubAPIRepository {
     id: u64,
     name: String,
     pub full_name: String,
+    #[serde(default)]
+    pub private: bool,
+    #[serde(default)]
+    pub archived: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitHubAPIOrganizationSecrets {
+    total_count: u64,
+    pub secrets: Vec<GitHubAPIOrganizationSecret>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIOrganizationSecret {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// `all`, `private` or `selected`.
+    pub visibility: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitHubAPISelectedRepositories {
+    total_count: u64,
+    pub repositories: Vec<GitHubAPIRepository>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,61 +75,564 @@ pub struct GitHubAPISecrets {
     pub secrets: Vec<GitHubAPISecret>
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GitHubAPISecret {
     pub name: String,
     pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>
+    pub updated_at: DateTime<Utc>,
+    /// Paths of the workflows that reference the secret. Only set when workflows are checked.
+    #[serde(default)]
+    pub workflows: Vec<String>,
+    /// Highest exposure of these workflows.
+    #[serde(default)]
+    pub exposure: Exposure,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIDeployKey {
+    pub id: u64,
+    pub title: String,
+    pub read_only: bool,
+    pub created_at: DateTime<Utc>,
+    /// Only reported by newer API versions.
+    #[serde(default)]
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl GitHubAPIDeployKey {
+    /// Deploy keys can't be updated, so they are validated like a secret that was
+    /// last updated when the key was added.
+    pub fn as_secret(&self) -> GitHubAPISecret {
+        GitHubAPISecret {
+            name: self.title.clone(),
+            created_at: self.created_at,
+            updated_at: self.created_at,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIWebhook {
+    pub id: u64,
+    #[serde(default)]
+    pub active: bool,
+    pub config: GitHubAPIWebhookConfig,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIWebhookConfig {
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Masked as `********` if a secret is configured.
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// `"0"` or `"1"`, some API versions return a number.
+    #[serde(default)]
+    pub insecure_ssl: Option<serde_json::Value>,
+}
+
+impl GitHubAPIWebhook {
+    pub fn has_secret(&self) -> bool {
+        self.config.secret.as_deref().is_some_and(|secret| !secret.is_empty())
+    }
+
+    pub fn is_insecure_ssl(&self) -> bool {
+        match &self.config.insecure_ssl {
+            Some(serde_json::Value::String(value)) => value == "1",
+            Some(serde_json::Value::Number(value)) => value.as_u64() == Some(1),
+            _ => false,
+        }
+    }
+
+    /// Host of the payload URL. The full URL is never shown, as it often contains a token.
+    pub fn host(&self) -> String {
+        self.config.url.as_deref()
+            .and_then(|url| reqwest::Url::parse(url).ok())
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| "unknown host".to_string())
+    }
+
+    /// The secret can't be read, so it is validated like a secret that was last
+    /// updated when the webhook configuration changed.
+    pub fn as_secret(&self) -> GitHubAPISecret {
+        GitHubAPISecret {
+            name: format!("webhook {}", self.id),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            ..Default::default()
+        }
+    }
+}
+
+/// An event of the organization audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIAuditLogEvent {
+    /// Unix time in milliseconds.
+    #[serde(rename = "@timestamp")]
+    pub timestamp: i64,
+    pub action: String,
+    #[serde(default)]
+    pub actor: Option<String>,
+    #[serde(default)]
+    pub repo: Option<String>,
+    #[serde(default)]
+    pub environment_name: Option<String>,
+    #[serde(default)]
+    secret_name: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+impl GitHubAPIAuditLogEvent {
+    /// Depending on the event, the secret name is reported as `secret_name` or `name`.
+    pub fn secret_name(&self) -> Option<String> {
+        self.secret_name.clone().or_else(|| self.name.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIUser {
+    pub login: String,
+}
+
+/// A fine-grained personal access token with access to the organization.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIPersonalAccessToken {
+    pub id: u64,
+    pub owner: GitHubAPIUser,
+    /// Only reported by newer API versions.
+    #[serde(default)]
+    pub token_name: Option<String>,
+    pub access_granted_at: DateTime<Utc>,
+    #[serde(default)]
+    pub token_expired: bool,
+    pub token_expires_at: Option<DateTime<Utc>>,
+    pub token_last_used_at: Option<DateTime<Utc>>,
+}
+
+/// A personal access token or SSH key authorized for SAML single sign-on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPICredentialAuthorization {
+    pub login: String,
+    pub credential_id: u64,
+    /// `personal access token` or `SSH key`.
+    pub credential_type: String,
+    pub credential_authorized_at: DateTime<Utc>,
+    pub credential_accessed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub authorized_credential_expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub authorized_credential_title: Option<String>,
+    #[serde(default)]
+    pub authorized_credential_note: Option<String>,
+    #[serde(default)]
+    pub token_last_eight: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitHubAPIVariables {
+    total_count: u64,
+    pub variables: Vec<GitHubAPIVariable>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIVariable {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitHubAPIEnvironments {
+    total_count: u64,
+    pub environments: Vec<GitHubAPIEnvironment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIEnvironment {
+    pub name: String,
+    #[serde(default)]
+    pub protection_rules: Vec<GitHubAPIProtectionRule>,
+    pub deployment_branch_policy: Option<GitHubAPIDeploymentBranchPolicy>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIProtectionRule {
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIDeploymentBranchPolicy {
+    pub protected_branches: bool,
+    pub custom_branch_policies: bool,
+}
+
+/// Entry of the repository contents API. `content` is only set for files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIContent {
+    pub name: String,
+    pub path: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAPIIssue {
+    pub number: u64,
+    pub title: String,
+    pub state: String,
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct GitHubAPIIssueRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignees: Option<Vec<String>>,
 }
 
 impl GitHubAPI {
-    pub fn new(url: Option<String>, token: Option<String>) -> GitHubAPI {
+    pub fn new(url: Option<String>, token: Option<String>) -> Result<GitHubAPI, InvalidConfiguration> {
 
         let github_url = url.unwrap_or_else(|| "https://api.github.com".to_string());
 
         if token.is_none() {
-            error!("GitHub API token is required");
-            std::process::exit(1);
+            return Err(InvalidConfiguration("GitHub API token is required.".to_string()));
         }
 
-        GitHubAPI {
+        Ok(GitHubAPI {
             url: Some(github_url),
             token,
             client: Client::new(),
-        }
+            stats: Arc::new(GitHubAPIStats {
+                requests: AtomicU64::new(0),
+                rate_limit_remaining: AtomicI64::new(-1),
+            }),
+        })
+    }
+
+    /// Number of requests sent since the client was created.
+    pub fn request_count(&self) -> u64 {
+        self.stats.requests.load(Ordering::Relaxed)
+    }
+
+    /// Remaining requests as reported by the last response, if known.
+    pub fn rate_limit_remaining(&self) -> Option<i64> {
+        let remaining = self.stats.rate_limit_remaining.load(Ordering::Relaxed);
+        (remaining >= 0).then_some(remaining)
     }
 
     fn get_url(&self, path: &str) -> String {
         format!("{}{}", self.url.as_ref().unwrap(), path)
     }
 
-    async fn request(&self, path: &str) -> Result<Response, Error> {
-        debug!("HTTP Request: {}", self.get_url(path));
-        let response = self.client.get(&self.get_url(path))
+    fn build_request(&self, method: Method, path: &str) -> RequestBuilder {
+        debug!("HTTP Request: {} {}", method, self.get_url(path));
+        self.client.request(method, self.get_url(path))
             .header("Authorization", format!("Bearer {}", self.token.clone().unwrap()))
             .header("User-Agent", "GHSO")
             .header("X-GitHub-Api-Version", "2022-11-28")
             .header("Accept", "application/vnd.github+json")
-            .send().await?;
+    }
+
+    async fn execute(&self, request: RequestBuilder) -> Result<Response, Error> {
+        GitHubAPI::check_status(self.send(request).await?).await
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        self.stats.requests.fetch_add(1, Ordering::Relaxed);
+        let response = request.send().await?;
+        let remaining = response.headers()
+            .get("x-ratelimit-remaining")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<i64>().ok());
+        if let Some(remaining) = remaining {
+            self.stats.rate_limit_remaining.store(remaining, Ordering::Relaxed);
+        }
+        Ok(response)
+    }
+
+    async fn check_status(response: Response) -> Result<Response, Error> {
         let status = response.status();
-        if  status != 200 {
+        if !status.is_success() {
             let error_response = response.json::<GitHubAPIError>().await?;
             return Err(Error::msg(format!("Failed to call GitHub API. Status code: {}. Message: {}", status, error_response.message)));
         }
         Ok(response)
     }
 
+    async fn request(&self, path: &str) -> Result<Response, Error> {
+        self.execute(self.build_request(Method::GET, path)).await
+    }
+
+    /// Like `request`, but returns `None` if the resource does not exist.
+    async fn request_optional(&self, path: &str) -> Result<Option<Response>, Error> {
+        let response = self.send(self.build_request(Method::GET, path)).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(GitHubAPI::check_status(response).await?))
+    }
+
+    /// Like `request`, but returns `None` if the resource does not exist or the token
+    /// may not read it. An exhausted rate limit is still an error.
+    async fn request_permitted(&self, path: &str) -> Result<Option<Response>, Error> {
+        let response = self.send(self.build_request(Method::GET, path)).await?;
+        let forbidden = response.status() == StatusCode::FORBIDDEN && self.rate_limit_remaining() != Some(0);
+        if forbidden || response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(GitHubAPI::check_status(response).await?))
+    }
+
+    /// Reads all pages of a list. `items` takes the list out of a page, which is either
+    /// the list itself or an object wrapping it.
+    async fn request_pages<P: DeserializeOwned, T>(&self, path: &str, per_page: usize, items: fn(P) -> Vec<T>) -> Result<Vec<T>, Error> {
+        self.request_filtered_pages(path, &[], per_page, items).await
+    }
+
+    /// Like `request_pages`, with query parameters that are sent with every page.
+    async fn request_filtered_pages<P: DeserializeOwned, T>(&self, path: &str, query: &[(&str, &str)], per_page: usize, items: fn(P) -> Vec<T>) -> Result<Vec<T>, Error> {
+        Ok(self.read_pages(path, query, per_page, false, items).await?.unwrap_or_default())
+    }
+
+    async fn read_pages<P: DeserializeOwned, T>(&self, path: &str, query: &[(&str, &str)], per_page: usize, optional: bool, items: fn(P) -> Vec<T>) -> Result<Option<Vec<T>>, Error> {
+        let mut all = Vec::new();
+        for page in 1.. {
+            let request = self.build_request(Method::GET, path)
+                .query(query)
+                .query(&[("per_page", per_page), ("page", page)]);
+            let response = self.send(request).await?;
+            if optional && page == 1 && response.status() == StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            let page_items = items(GitHubAPI::check_status(response).await?.json::<P>().await?);
+            let last_page = page_items.len() < per_page;
+            all.extend(page_items);
+            if last_page {
+                break;
+            }
+        }
+        Ok(Some(all))
+    }
+
+    async fn request_with_body<T: Serialize>(&self, method: Method, path: &str, body: &T) -> Result<Response, Error> {
+        self.execute(self.build_request(method, path).json(body)).await
+    }
+
     pub async fn get_repositories(&self, org_name: &str) -> Result<Vec<GitHubAPIRepository>, Error> {
         let response = self.request(format!("/orgs/{}/repos", org_name).as_str()).await?;
         Ok(response.json::<Vec<GitHubAPIRepository>>().await?)
     }
 
+    pub async fn get_repository(&self, full_name: &str) -> Result<GitHubAPIRepository, Error> {
+        let response = self.request(format!("/repos/{}", full_name).as_str()).await?;
+        Ok(response.json::<GitHubAPIRepository>().await?)
+    }
+
     pub async fn get_secrets(&self, repository: &GitHubAPIRepository) -> Result<GitHubAPISecrets, Error> {
         let response = self.request(
             format!("/repos/{}/actions/secrets", repository.full_name).as_str()
         ).await?;
         Ok(response.json::<GitHubAPISecrets>().await?)
     }
+
+    pub async fn get_secret(&self, repository: &str, name: &str) -> Result<GitHubAPISecret, Error> {
+        let response = self.request(
+            format!("/repos/{}/actions/secrets/{}", repository, name).as_str()
+        ).await?;
+        Ok(response.json::<GitHubAPISecret>().await?)
+    }
+
+    pub async fn get_environments(&self, repository: &str) -> Result<Vec<GitHubAPIEnvironment>, Error> {
+        Ok(self.read_pages(
+            format!("/repos/{}/environments", repository).as_str(),
+            &[],
+            100,
+            true,
+            |page: GitHubAPIEnvironments| page.environments
+        ).await?.unwrap_or_default())
+    }
+
+    pub async fn get_environment_secrets(&self, repository: &str, environment: &str) -> Result<GitHubAPISecrets, Error> {
+        let response = self.request(
+            format!("/repos/{}/environments/{}/secrets", repository, path_segment(environment)).as_str()
+        ).await?;
+        Ok(response.json::<GitHubAPISecrets>().await?)
+    }
+
+    /// Organization secrets that are shared with the repository.
+    pub async fn get_organization_secrets(&self, repository: &str) -> Result<GitHubAPISecrets, Error> {
+        let response = self.request(
+            format!("/repos/{}/actions/organization-secrets", repository).as_str()
+        ).await?;
+        Ok(response.json::<GitHubAPISecrets>().await?)
+    }
+
+    pub async fn get_organization_secret_list(&self, org_name: &str) -> Result<Vec<GitHubAPIOrganizationSecret>, Error> {
+        self.request_pages(
+            format!("/orgs/{}/actions/secrets", org_name).as_str(),
+            100,
+            |page: GitHubAPIOrganizationSecrets| page.secrets
+        ).await
+    }
+
+    /// Repositories an organization secret with `selected` visibility is shared with.
+    pub async fn get_organization_secret_repositories(&self, org_name: &str, secret: &str) -> Result<Vec<GitHubAPIRepository>, Error> {
+        self.request_pages(
+            format!("/orgs/{}/actions/secrets/{}/repositories", org_name, secret).as_str(),
+            100,
+            |page: GitHubAPISelectedRepositories| page.repositories
+        ).await
+    }
+
+    pub async fn get_deploy_keys(&self, repository: &str) -> Result<Vec<GitHubAPIDeployKey>, Error> {
+        let response = self.request(format!("/repos/{}/keys", repository).as_str()).await?;
+        Ok(response.json::<Vec<GitHubAPIDeployKey>>().await?)
+    }
+
+    /// Requires admin access to the repository, `None` without it.
+    pub async fn get_webhooks(&self, repository: &str) -> Result<Option<Vec<GitHubAPIWebhook>>, Error> {
+        let Some(response) = self.request_permitted(format!("/repos/{}/hooks", repository).as_str()).await? else {
+            return Ok(None);
+        };
+        Ok(Some(response.json::<Vec<GitHubAPIWebhook>>().await?))
+    }
+
+    /// Requires an organization owner token, `None` without it.
+    pub async fn get_organization_webhooks(&self, org_name: &str) -> Result<Option<Vec<GitHubAPIWebhook>>, Error> {
+        let Some(response) = self.request_permitted(format!("/orgs/{}/hooks", org_name).as_str()).await? else {
+            return Ok(None);
+        };
+        Ok(Some(response.json::<Vec<GitHubAPIWebhook>>().await?))
+    }
+
+    /// Events of the organization audit log matching the search phrase, newest first.
+    pub async fn get_audit_log(&self, org_name: &str, phrase: &str, page: u32) -> Result<Vec<GitHubAPIAuditLogEvent>, Error> {
+        let response = self.execute(self.build_request(Method::GET, &format!("/orgs/{}/audit-log", org_name))
+            .query(&[("phrase", phrase), ("order", "desc"), ("per_page", "100"), ("page", &page.to_string())])
+        ).await?;
+        Ok(response.json::<Vec<GitHubAPIAuditLogEvent>>().await?)
+    }
+
+    /// Requires a GitHub App installation token of the organization.
+    pub async fn get_personal_access_tokens(&self, org_name: &str) -> Result<Vec<GitHubAPIPersonalAccessToken>, Error> {
+        self.request_pages(
+            format!("/orgs/{}/personal-access-tokens", org_name).as_str(),
+            100,
+            |tokens: Vec<GitHubAPIPersonalAccessToken>| tokens
+        ).await
+    }
+
+    /// Organizations without SAML single sign-on have no credential authorizations.
+    pub async fn get_credential_authorizations(&self, org_name: &str) -> Result<Vec<GitHubAPICredentialAuthorization>, Error> {
+        Ok(self.read_pages(
+            format!("/orgs/{}/credential-authorizations", org_name).as_str(),
+            &[],
+            100,
+            true,
+            |authorizations: Vec<GitHubAPICredentialAuthorization>| authorizations
+        ).await?.unwrap_or_default())
+    }
+
+    pub async fn get_variables(&self, repository: &str) -> Result<Vec<GitHubAPIVariable>, Error> {
+        self.request_pages(
+            format!("/repos/{}/actions/variables", repository).as_str(),
+            VARIABLES_PER_PAGE,
+            |page: GitHubAPIVariables| page.variables
+        ).await
+    }
+
+    pub async fn get_environment_variables(&self, repository: &str, environment: &str) -> Result<Vec<GitHubAPIVariable>, Error> {
+        self.request_pages(
+            format!("/repos/{}/environments/{}/variables", repository, path_segment(environment)).as_str(),
+            VARIABLES_PER_PAGE,
+            |page: GitHubAPIVariables| page.variables
+        ).await
+    }
+
+    pub async fn get_organization_variables(&self, org_name: &str) -> Result<Vec<GitHubAPIVariable>, Error> {
+        self.request_pages(
+            format!("/orgs/{}/actions/variables", org_name).as_str(),
+            VARIABLES_PER_PAGE,
+            |page: GitHubAPIVariables| page.variables
+        ).await
+    }
+
+    /// Lists a directory of the default branch. A missing directory is empty.
+    pub async fn get_directory(&self, repository: &str, path: &str) -> Result<Vec<GitHubAPIContent>, Error> {
+        let Some(response) = self.request_optional(
+            format!("/repos/{}/contents/{}", repository, path).as_str()
+        ).await? else {
+            return Ok(Vec::new());
+        };
+        Ok(response.json::<Vec<GitHubAPIContent>>().await?)
+    }
+
+    /// Reads a file of the default branch, `None` if it does not exist.
+    pub async fn get_file(&self, repository: &str, path: &str) -> Result<Option<String>, Error> {
+        let Some(response) = self.request_optional(
+            format!("/repos/{}/contents/{}", repository, path).as_str()
+        ).await? else {
+            return Ok(None);
+        };
+        let content = response.json::<GitHubAPIContent>().await?;
+        Ok(Some(decode_content(content.content.as_deref().unwrap_or_default())?))
+    }
+
+    pub async fn get_open_issues(&self, repository: &str, label: &str) -> Result<Vec<GitHubAPIIssue>, Error> {
+        self.request_filtered_pages(
+            format!("/repos/{}/issues", repository).as_str(),
+            &[("state", "open"), ("labels", label)],
+            100,
+            |issues: Vec<GitHubAPIIssue>| issues
+        ).await
+    }
+
+    pub async fn create_issue(&self, repository: &str, issue: &GitHubAPIIssueRequest) -> Result<GitHubAPIIssue, Error> {
+        let response = self.request_with_body(
+            Method::POST,
+            format!("/repos/{}/issues", repository).as_str(),
+            issue
+        ).await?;
+        Ok(response.json::<GitHubAPIIssue>().await?)
+    }
+
+    pub async fn update_issue(&self, repository: &str, number: u64, issue: &GitHubAPIIssueRequest) -> Result<GitHubAPIIssue, Error> {
+        let response = self.request_with_body(
+            Method::PATCH,
+            format!("/repos/{}/issues/{}", repository, number).as_str(),
+            issue
+        ).await?;
+        Ok(response.json::<GitHubAPIIssue>().await?)
+    }
+}
+
+/// Percent-encodes a name, e.g. of an environment, for use as a single path segment.
+fn path_segment(name: &str) -> String {
+    let mut url = Url::parse("https://api.github.com").unwrap();
+    url.path_segments_mut().unwrap().push(name);
+    url.path().trim_start_matches('/').to_string()
+}
+
+/// Decodes the base64 content of a file, which GitHub wraps into lines.
+fn decode_content(content: &str) -> Result<String, Error> {
+    let content: String = content.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = base64::engine::general_purpose::STANDARD.decode(content)?;
+    Ok(String::from_utf8(bytes)?)
 }
 
 #[cfg(test)]
@@ -96,7 +644,7 @@ mod tests {
         let github_api = GitHubAPI::new(
             Some("https://api.github.com".to_string()),
             Some("token".to_string())
-        );
+        ).unwrap();
         assert_eq!(github_api.get_url("/path"), "https://api.github.com/path");
         assert_eq!(github_api.get_url("/a/very/long/path"), "https://api.github.com/a/very/long/path");
     }
@@ -106,8 +654,24 @@ mod tests {
         let github_api = GitHubAPI::new(
             Some("https://api.github.com".to_string()),
             Some("token".to_string())
-        );
+        ).unwrap();
         let response = github_api.request("/403").await;
         assert!(response.is_err());
     }
+
+    #[test]
+    fn test_missing_token() {
+        assert!(GitHubAPI::new(None, None).is_err());
+    }
+
+    #[test]
+    fn test_path_segment() {
+        assert_eq!(path_segment("production"), "production");
+        assert_eq!(path_segment("staging eu/west"), "staging%20eu%2Fwest");
+    }
+
+    #[test]
+    fn test_decode_content() {
+        assert_eq!(decode_content("b246IHB1c2gK\nam9iczoge30K\n").unwrap(), "on: push\njobs: {}\n");
+    }
 }
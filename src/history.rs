@@ -0,0 +1,292 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use anyhow::{Context, Error};
+use chrono::prelude::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::github_api::{GitHubAPIRepository, GitHubAPISecret};
+use crate::validator::{ValidatorResult, ValidatorState};
+
+/// A single finding of a run as stored in the history file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub scanned_at: DateTime<Utc>,
+    pub repository: String,
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub state: ValidatorState,
+    pub days_age: i64,
+    pub days_left: i64,
+    pub days_overdue: i64,
+}
+
+/// The repositories that were scanned in a run, stored next to its records, so
+/// a repository without secrets can be told apart from one that was not scanned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HistoryRun {
+    scanned_at: DateTime<Utc>,
+    scanned_repositories: BTreeSet<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HistoryLine {
+    Run(HistoryRun),
+    Record(HistoryRecord),
+}
+
+/// Records of a run and the repositories it scanned.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub records: Vec<HistoryRecord>,
+    pub scanned_repositories: HashSet<String>,
+}
+
+impl Snapshot {
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty() && self.scanned_repositories.is_empty()
+    }
+}
+
+/// Compliance of a single run.
+#[derive(Debug, PartialEq)]
+pub struct TrendEntry {
+    pub scanned_at: DateTime<Utc>,
+    pub total: usize,
+    pub expired: usize,
+    pub expires_soon: usize,
+    pub not_expired: usize,
+    pub ignored: usize,
+}
+
+impl TrendEntry {
+    /// Share of not ignored secrets that are not expired, in percent.
+    pub fn compliance(&self) -> f64 {
+        let relevant = self.total - self.ignored;
+        if relevant == 0 {
+            return 100.0;
+        }
+        (relevant - self.expired) as f64 * 100.0 / relevant as f64
+    }
+}
+
+/// Append-only JSON lines store of all findings.
+pub struct History {
+    path: PathBuf,
+    scanned_at: DateTime<Utc>,
+    scanned_repositories: BTreeSet<String>,
+    pending: Vec<HistoryRecord>,
+}
+
+impl History {
+    pub fn new(path: PathBuf) -> Self {
+        History {
+            path,
+            scanned_at: Utc::now(),
+            scanned_repositories: BTreeSet::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Starts a new run. All records until the next `save` share its timestamp.
+    pub fn start_run(&mut self) {
+        self.scanned_at = Utc::now();
+        self.scanned_repositories.clear();
+        self.pending.clear();
+    }
+
+    /// Marks a repository as scanned in the current run, even if it has no secrets.
+    pub fn scanned(&mut self, repository: &GitHubAPIRepository) {
+        self.scanned_repositories.insert(repository.full_name.clone());
+    }
+
+    pub fn record(&mut self, result: &ValidatorResult, secret: &GitHubAPISecret, repository: &GitHubAPIRepository) {
+        self.pending.push(HistoryRecord {
+            scanned_at: self.scanned_at,
+            repository: repository.full_name.clone(),
+            secret: secret.name.clone(),
+            created_at: secret.created_at,
+            updated_at: secret.updated_at,
+            state: result.state,
+            days_age: result.days_age,
+            days_left: result.days_left,
+            days_overdue: result.days_overdue,
+        });
+    }
+
+    /// Records of the current run that have not been saved yet.
+    pub fn pending(&self) -> Snapshot {
+        Snapshot {
+            records: self.pending.clone(),
+            scanned_repositories: self.scanned_repositories.iter().cloned().collect(),
+        }
+    }
+
+    /// Records of the most recent saved run. Runs written before the scanned
+    /// repositories were stored fall back to the repositories of their records.
+    pub fn last_snapshot(&self) -> Result<Snapshot, Error> {
+        let (runs, records) = self.read()?;
+        let last = records.iter().map(|record| record.scanned_at)
+            .chain(runs.iter().map(|run| run.scanned_at))
+            .max();
+        let Some(last) = last else {
+            return Ok(Snapshot::default());
+        };
+        let records: Vec<_> = records.into_iter().filter(|record| record.scanned_at == last).collect();
+        let scanned_repositories = match runs.into_iter().find(|run| run.scanned_at == last) {
+            Some(run) => run.scanned_repositories.into_iter().collect(),
+            None => records.iter().map(|record| record.repository.clone()).collect(),
+        };
+        Ok(Snapshot { records, scanned_repositories })
+    }
+
+    /// Appends the current run and its records to the history file.
+    pub fn save(&mut self) -> Result<(), Error> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open history file {}", self.path.display()))?;
+        let run = HistoryRun {
+            scanned_at: self.scanned_at,
+            scanned_repositories: std::mem::take(&mut self.scanned_repositories),
+        };
+        writeln!(file, "{}", serde_json::to_string(&run)?)?;
+        for record in self.pending.drain(..) {
+            writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        }
+        Ok(())
+    }
+
+    pub fn load(&self) -> Result<Vec<HistoryRecord>, Error> {
+        Ok(self.read()?.1)
+    }
+
+    fn read(&self) -> Result<(Vec<HistoryRun>, Vec<HistoryRecord>), Error> {
+        if !self.path.exists() {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        let file = std::fs::File::open(&self.path)
+            .with_context(|| format!("Failed to open history file {}", self.path.display()))?;
+        let mut runs = Vec::new();
+        let mut records = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let line = serde_json::from_str::<HistoryLine>(&line)
+                .with_context(|| format!("Invalid record in line {} of {}", number + 1, self.path.display()))?;
+            match line {
+                HistoryLine::Run(run) => runs.push(run),
+                HistoryLine::Record(record) => records.push(record),
+            }
+        }
+        Ok((runs, records))
+    }
+
+    pub fn secret_history(&self, repository: &str, secret: &str) -> Result<Vec<HistoryRecord>, Error> {
+        Ok(self.load()?
+            .into_iter()
+            .filter(|record| record.repository == repository && record.secret == secret)
+            .collect())
+    }
+
+    pub fn compliance_trend(&self) -> Result<Vec<TrendEntry>, Error> {
+        Ok(History::trend(&self.load()?))
+    }
+
+    fn trend(records: &[HistoryRecord]) -> Vec<TrendEntry> {
+        let mut runs: BTreeMap<DateTime<Utc>, TrendEntry> = BTreeMap::new();
+        for record in records {
+            let entry = runs.entry(record.scanned_at).or_insert_with(|| TrendEntry {
+                scanned_at: record.scanned_at,
+                total: 0,
+                expired: 0,
+                expires_soon: 0,
+                not_expired: 0,
+                ignored: 0,
+            });
+            entry.total += 1;
+            match record.state {
+                ValidatorState::Expired => entry.expired += 1,
+                ValidatorState::ExpiresSoon => entry.expires_soon += 1,
+                ValidatorState::NotExpired => entry.not_expired += 1,
+                ValidatorState::Ignored => entry.ignored += 1,
+            }
+        }
+        runs.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn record(scanned_at: DateTime<Utc>, secret: &str, state: ValidatorState) -> HistoryRecord {
+        HistoryRecord {
+            scanned_at,
+            repository: "org/repo".to_string(),
+            secret: secret.to_string(),
+            created_at: scanned_at,
+            updated_at: scanned_at,
+            state,
+            days_age: 0,
+            days_left: 0,
+            days_overdue: 0,
+        }
+    }
+
+    #[test]
+    fn test_trend() {
+        let first = Utc::now() - Duration::days(1);
+        let second = Utc::now();
+        let records = vec![
+            record(second, "A", ValidatorState::NotExpired),
+            record(first, "A", ValidatorState::Expired),
+            record(first, "B", ValidatorState::NotExpired),
+            record(first, "C", ValidatorState::Ignored),
+        ];
+        let trend = History::trend(&records);
+        assert_eq!(trend.len(), 2);
+        assert_eq!(trend[0].scanned_at, first);
+        assert_eq!(trend[0].expired, 1);
+        assert_eq!(trend[0].compliance(), 50.0);
+        assert_eq!(trend[1].compliance(), 100.0);
+    }
+
+    #[test]
+    fn test_last_snapshot() {
+        let path = std::env::temp_dir().join(format!("ghso-history-{}.jsonl", std::process::id()));
+        let first = Utc::now() - Duration::days(1);
+        let second = Utc::now();
+        let run = HistoryRun {
+            scanned_at: second,
+            scanned_repositories: BTreeSet::from(["org/repo".to_string(), "org/empty".to_string()]),
+        };
+        let lines = [
+            // Written before the scanned repositories were stored.
+            serde_json::to_string(&record(first, "A", ValidatorState::Expired)).unwrap(),
+            serde_json::to_string(&run).unwrap(),
+            serde_json::to_string(&record(second, "A", ValidatorState::NotExpired)).unwrap(),
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let history = History::new(path.clone());
+
+        let snapshot = history.last_snapshot().unwrap();
+        assert_eq!(snapshot.records.len(), 1);
+        assert_eq!(snapshot.records[0].scanned_at, second);
+        assert!(snapshot.scanned_repositories.contains("org/empty"));
+        assert_eq!(history.load().unwrap().len(), 2);
+
+        std::fs::write(&path, &lines[0]).unwrap();
+        let snapshot = history.last_snapshot().unwrap();
+        assert_eq!(snapshot.scanned_repositories, HashSet::from(["org/repo".to_string()]));
+        std::fs::remove_file(path).unwrap();
+    }
+}
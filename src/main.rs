@@ -4,60 +4,237 @@ mod config;
 mod github_api;
 mod validator;
 mod notifier;
+mod history;
+mod changes;
+mod daemon;
+mod metrics;
+mod commands;
+mod workflows;
+mod audit;
+mod credentials;
+mod attribution;
+
+use std::process::ExitCode;
 
 use anyhow::{Context, Error};
 use clap::Parser;
-use cli::Cli;
+use cli::{Cli, Command, ConfigCommand};
 use tracing_subscriber::FmtSubscriber;
-use tracing::{info, error};
+use tracing::{info, error, warn};
+
+use config::{InvalidConfiguration, ObserverConfig};
+use observer::{Observer, ScanSummary};
+use history::History;
+use validator::ValidatorState;
+
+
+/// Exit codes of the process, so CI can gate on the result of a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Compliant = 0,
+    ExpiresSoon = 1,
+    Expired = 2,
+    ConfigurationError = 3,
+    ScanFailed = 4,
+    SevereFindings = 5,
+}
+
+impl Outcome {
+    fn from_summary(summary: &ScanSummary, config: &ObserverConfig) -> Self {
+        // The findings of skipped repositories are unknown, so the scan can't pass.
+        if !summary.failed_repositories.is_empty() && config.fail_on_skipped_repositories {
+            Outcome::ScanFailed
+        } else if summary.expired > 0 {
+            Outcome::Expired
+        } else if summary.severe_findings > 0 {
+            Outcome::SevereFindings
+        } else if summary.expires_soon > 0 && config.fail_on_expires_soon {
+            Outcome::ExpiresSoon
+        } else {
+            Outcome::Compliant
+        }
+    }
+}
 
-use observer::Observer;
+enum Failure {
+    Configuration(Error),
+    Scan(Error),
+}
 
+impl Failure {
+    /// Errors raised while scanning are only configuration errors if they are
+    /// marked as such, everything else is caused by the GitHub API or notifiers.
+    fn from_scan(error: Error) -> Self {
+        if error.downcast_ref::<InvalidConfiguration>().is_some() {
+            Failure::Configuration(error)
+        } else {
+            Failure::Scan(error)
+        }
+    }
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Error> {
+async fn main() -> ExitCode {
 
-    let cli = Cli::parse();
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            // clap exits with 2 on usage errors, which means expired secrets here.
+            return if e.exit_code() == 0 {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(Outcome::ConfigurationError as u8)
+            };
+        }
+    };
 
+    // Logs go to stderr, so the output of `list`, `history` and `config check` can be piped.
     let subscriber = FmtSubscriber::builder()
         .with_max_level(cli.log_level)
+        .with_writer(std::io::stderr)
         .finish();
 
     tracing::subscriber::set_global_default(subscriber)
         .expect("setting default subscriber failed");
 
+    let outcome = match run(cli).await {
+        Ok(outcome) => outcome,
+        Err(Failure::Configuration(e)) => {
+            error!("Invalid configuration. Reason: {:#}", e);
+            Outcome::ConfigurationError
+        },
+        Err(Failure::Scan(e)) => {
+            error!("Observer failed. Reason: {:#}", e);
+            Outcome::ScanFailed
+        }
+    };
+    ExitCode::from(outcome as u8)
+}
+
+async fn run(cli: Cli) -> Result<Outcome, Failure> {
     info!("Loading settings.");
-    let mut config = config::Configuration::new().context("Failed to load settings")?;
+    let mut config = config::Configuration::new(&cli.config)
+        .context("Failed to load settings")
+        .map_err(Failure::Configuration)?;
 
     // Override settings with CLI params
     if cli.organization.is_some() {
         config.github.organization = cli.organization;
     }
+    if !cli.repositories.is_empty() {
+        config.github.repositories = Some(cli.repositories);
+    }
     if let Some (notifier_type) = cli.notifier_type {
         config.notifier.notifier_type = notifier_type;
     }
+    config.apply_github_actions(|name| std::env::var(name).ok());
+    let command = cli.command.unwrap_or(Command::Scan);
+    // The history is read locally, it needs neither a valid GitHub setup nor a token.
+    if !matches!(command, Command::History(_)) {
+        config.validate().map_err(|e| Failure::Configuration(e.into()))?;
+    }
+    if !matches!(command, Command::History(_) | Command::Config(_)) {
+        config.github.resolve_token().map_err(Failure::Configuration)?;
+    }
 
-    let observer = Observer::new(config).await;
-
-    let mut observer = match observer {
-        Ok(observer) => {
-            info!("Observer initialized successfully.");
-            observer
+    let mut schedule = None;
+    let mut metrics_address = None;
+    match command {
+        Command::Scan => {
+            let notifier = &config.notifier;
+            if (notifier.pagerduty_routing_key.is_some() || notifier.opsgenie_api_key.is_some()) && notifier.state_file.is_none() {
+                warn!("PagerDuty or Opsgenie alerting is enabled without notifier.state_file. Open alerts are not remembered and won't be resolved by a later run.");
+            }
         },
-        Err(e) => {
-            error!("Observer failed to initialize. Reason: {:?}", e);
-            panic!("Abort program.")
-        }
-    };
-
-    match observer.run().await {
-        Ok(()) => {
-            info!("Observer finished successfully.");
-            Ok(())
+        Command::List(command) => {
+            commands::list(&config, command).await.map_err(Failure::from_scan)?;
+            return Ok(Outcome::Compliant);
         },
-        Err(e) => {
-            error!("Observer failed. Reason: {:?}", e);
-            panic!("Abort program.")
+        Command::Explain { repository, secret } => {
+            let state = commands::explain(&config, &repository, &secret).await.map_err(Failure::from_scan)?;
+            let summary = ScanSummary {
+                expired: (state == ValidatorState::Expired) as usize,
+                expires_soon: (state == ValidatorState::ExpiresSoon) as usize,
+                ..Default::default()
+            };
+            return Ok(Outcome::from_summary(&summary, &config.observer));
+        },
+        Command::Config(ConfigCommand::Check) => {
+            commands::check_config(&config).map_err(Failure::Configuration)?;
+            return Ok(Outcome::Compliant);
+        },
+        Command::History(command) => {
+            let Some(file) = config.history.file else {
+                return Err(Failure::Configuration(anyhow::anyhow!("No history file configured. Please set history.file.")));
+            };
+            commands::print_history(&History::new(file.into()), command).map_err(Failure::Scan)?;
+            return Ok(Outcome::Compliant);
+        },
+        Command::Serve { schedule: cron, interval_minutes } => {
+            if cron.is_some() || interval_minutes.is_some() {
+                config.daemon.schedule = cron;
+                config.daemon.interval_minutes = interval_minutes;
+            }
+            schedule = Some(daemon::Schedule::from_config(&config.daemon).map_err(Failure::Configuration)?);
+            if let Some(listen) = &config.metrics.listen {
+                metrics_address = Some(listen.parse()
+                    .with_context(|| format!("Invalid metrics.listen address '{}'", listen))
+                    .map_err(Failure::Configuration)?);
+            }
+            // Keep the notification state in memory between runs.
+            config.notifier.deduplicate = true;
         }
     }
-}
\ No newline at end of file
+
+    let observer_config = config.observer.clone();
+    let mut observer = Observer::new(config).await
+        .context("Observer failed to initialize")
+        .map_err(Failure::Configuration)?;
+    info!("Observer initialized successfully.");
+
+    if let Some(schedule) = schedule {
+        daemon::serve(observer, schedule, metrics_address).await.map_err(Failure::from_scan)?;
+        return Ok(Outcome::Compliant);
+    }
+
+    let summary = observer.run().await.map_err(Failure::from_scan)?;
+    info!("Observer finished successfully.");
+    Ok(Outcome::from_summary(&summary, &observer_config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome_from_summary() {
+        let config = ObserverConfig { fail_on_expires_soon: true, fail_on_skipped_repositories: true, ..Default::default() };
+        let summary = ScanSummary { expired: 1, expires_soon: 1, ..Default::default() };
+        assert_eq!(Outcome::from_summary(&summary, &config), Outcome::Expired);
+        let summary = ScanSummary { expired: 0, expires_soon: 1, ..Default::default() };
+        assert_eq!(Outcome::from_summary(&summary, &config), Outcome::ExpiresSoon);
+        let lenient = ObserverConfig { fail_on_expires_soon: false, ..config.clone() };
+        assert_eq!(Outcome::from_summary(&summary, &lenient), Outcome::Compliant);
+        assert_eq!(Outcome::from_summary(&ScanSummary::default(), &config), Outcome::Compliant);
+        let summary = ScanSummary { severe_findings: 1, expires_soon: 1, ..Default::default() };
+        assert_eq!(Outcome::from_summary(&summary, &config), Outcome::SevereFindings);
+        let summary = ScanSummary { expired: 1, severe_findings: 1, ..Default::default() };
+        assert_eq!(Outcome::from_summary(&summary, &config), Outcome::Expired);
+    }
+
+    #[test]
+    fn test_outcome_with_skipped_repositories() {
+        let config = ObserverConfig { fail_on_skipped_repositories: true, ..Default::default() };
+        let summary = ScanSummary {
+            failed_repositories: vec![observer::RepositoryFailure {
+                repository: "my_org/my_repo".to_string(),
+                reason: "403 Forbidden".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert_eq!(Outcome::from_summary(&summary, &config), Outcome::ScanFailed);
+        let lenient = ObserverConfig { fail_on_skipped_repositories: false, ..config };
+        assert_eq!(Outcome::from_summary(&summary, &lenient), Outcome::Compliant);
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use anyhow::{Context, Error};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use tokio::sync::watch;
+use tracing::info;
+
+use crate::changes::ChangeReport;
+use crate::validator::ValidatorState;
+
+pub type SharedMetrics = Arc<RwLock<Metrics>>;
+
+/// Labels of a `ghso_secret_age_days` series.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct SecretLabels {
+    repository: String,
+    secret: String,
+    kind: &'static str,
+}
+
+/// Metrics of the most recent scan in the Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    secrets: BTreeMap<SecretLabels, i64>,
+    states: BTreeMap<&'static str, usize>,
+    scan_duration_seconds: f64,
+    last_scan_timestamp: i64,
+    api_requests: u64,
+    rate_limit_remaining: Option<i64>,
+    changes: Option<BTreeMap<&'static str, usize>>,
+}
+
+fn state_label(state: &ValidatorState) -> &'static str {
+    match state {
+        ValidatorState::Expired => "expired",
+        ValidatorState::ExpiresSoon => "expires_soon",
+        ValidatorState::NotExpired => "not_expired",
+        ValidatorState::Ignored => "ignored",
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let mut metrics = Metrics::default();
+        // Export all states, so a state without secrets is reported as 0.
+        for state in [ValidatorState::Expired, ValidatorState::ExpiresSoon, ValidatorState::NotExpired, ValidatorState::Ignored] {
+            metrics.states.insert(state_label(&state), 0);
+        }
+        metrics
+    }
+
+    pub fn record_secret(&mut self, repository: &str, secret: &str, kind: &'static str, state: &ValidatorState, days_age: i64) {
+        // Deploy keys and credentials can share a name. A series must be unique,
+        // so only the oldest of them is exported.
+        let labels = SecretLabels {
+            repository: repository.to_string(),
+            secret: secret.to_string(),
+            kind,
+        };
+        self.secrets.entry(labels)
+            .and_modify(|age| *age = (*age).max(days_age))
+            .or_insert(days_age);
+        *self.states.entry(state_label(state)).or_default() += 1;
+    }
+
+    /// Exports the number of changes since the previous run, so the rotation
+    /// frequency can be derived from the history of the gauge.
+    pub fn record_changes(&mut self, report: &ChangeReport) {
+        self.changes = Some(BTreeMap::from([
+            ("rotated", report.rotated.len()),
+            ("created", report.created.len()),
+            ("deleted", report.deleted.len()),
+            ("newly_expired", report.newly_expired.len()),
+        ]));
+    }
+
+    pub fn finish_scan(&mut self, duration_seconds: f64, timestamp: i64, api_requests: u64, rate_limit_remaining: Option<i64>) {
+        self.scan_duration_seconds = duration_seconds;
+        self.last_scan_timestamp = timestamp;
+        self.api_requests = api_requests;
+        self.rate_limit_remaining = rate_limit_remaining;
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        // Writing to a String can't fail.
+        let _ = self.write(&mut out);
+        out
+    }
+
+    fn write(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(out, "# HELP ghso_secret_age_days Days since the secret was last updated.")?;
+        writeln!(out, "# TYPE ghso_secret_age_days gauge")?;
+        for (secret, days_age) in self.secrets.iter() {
+            writeln!(
+                out,
+                "ghso_secret_age_days{{repo=\"{}\",secret=\"{}\",kind=\"{}\"}} {}",
+                escape_label(&secret.repository), escape_label(&secret.secret), secret.kind, days_age
+            )?;
+        }
+        writeln!(out, "# HELP ghso_secrets_total Number of secrets per state.")?;
+        writeln!(out, "# TYPE ghso_secrets_total gauge")?;
+        for (state, count) in self.states.iter() {
+            writeln!(out, "ghso_secrets_total{{state=\"{}\"}} {}", state, count)?;
+        }
+        if let Some(changes) = &self.changes {
+            writeln!(out, "# HELP ghso_secret_changes Secrets that changed since the previous scan.")?;
+            writeln!(out, "# TYPE ghso_secret_changes gauge")?;
+            for (change, count) in changes.iter() {
+                writeln!(out, "ghso_secret_changes{{change=\"{}\"}} {}", change, count)?;
+            }
+        }
+        writeln!(out, "# HELP ghso_scan_duration_seconds Duration of the last scan.")?;
+        writeln!(out, "# TYPE ghso_scan_duration_seconds gauge")?;
+        writeln!(out, "ghso_scan_duration_seconds {}", self.scan_duration_seconds)?;
+        writeln!(out, "# HELP ghso_last_scan_timestamp_seconds Unix time of the last finished scan.")?;
+        writeln!(out, "# TYPE ghso_last_scan_timestamp_seconds gauge")?;
+        writeln!(out, "ghso_last_scan_timestamp_seconds {}", self.last_scan_timestamp)?;
+        writeln!(out, "# HELP ghso_github_api_requests_total Requests sent to the GitHub API.")?;
+        writeln!(out, "# TYPE ghso_github_api_requests_total counter")?;
+        writeln!(out, "ghso_github_api_requests_total {}", self.api_requests)?;
+        if let Some(remaining) = self.rate_limit_remaining {
+            writeln!(out, "# HELP ghso_github_rate_limit_remaining Remaining GitHub API requests in the current rate limit window.")?;
+            writeln!(out, "# TYPE ghso_github_rate_limit_remaining gauge")?;
+            writeln!(out, "ghso_github_rate_limit_remaining {}", remaining)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the metrics for the node exporter textfile collector. The file is
+    /// replaced atomically, so the collector never reads a partial file.
+    pub fn write_textfile(&self, path: &Path) -> Result<(), Error> {
+        let tmp = path.with_extension("prom.tmp");
+        std::fs::write(&tmp, self.render())
+            .with_context(|| format!("Failed to write metrics file {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to write metrics file {}", path.display()))?;
+        Ok(())
+    }
+}
+
+async fn handle(request: Request<Body>, metrics: SharedMetrics) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => {
+            let body = metrics.read().map(|m| m.render()).unwrap_or_default();
+            Response::builder()
+                .header("Content-Type", "text/plain; version=0.0.4")
+                .body(Body::from(body))
+        },
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    };
+    Ok(response.expect("static response is valid"))
+}
+
+/// Binds the `/metrics` endpoint and returns the server, which runs until `true`
+/// is sent on the shutdown channel. Binding early reports a busy port at startup.
+pub fn metrics_server(
+    address: SocketAddr,
+    metrics: SharedMetrics,
+    mut shutdown: watch::Receiver<bool>
+) -> Result<impl Future<Output = Result<(), Error>>, Error> {
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| handle(request, metrics.clone())))
+        }
+    });
+    let server = Server::try_bind(&address)
+        .with_context(|| format!("Failed to bind metrics endpoint to {}", address))?
+        .serve(make_service)
+        .with_graceful_shutdown(async move {
+            while !*shutdown.borrow() {
+                if shutdown.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+    info!("Serving metrics on http://{}/metrics.", address);
+    Ok(async move { Ok(server.await?) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut metrics = Metrics::new();
+        metrics.record_secret("org/repo", "SECRET", "repository", &ValidatorState::Expired, 100);
+        metrics.finish_scan(1.5, 1700000000, 3, Some(4997));
+        let rendered = metrics.render();
+        assert!(rendered.contains("ghso_secret_age_days{repo=\"org/repo\",secret=\"SECRET\",kind=\"repository\"} 100\n"));
+        assert!(rendered.contains("ghso_secrets_total{state=\"expired\"} 1\n"));
+        assert!(rendered.contains("ghso_secrets_total{state=\"not_expired\"} 0\n"));
+        assert!(rendered.contains("ghso_scan_duration_seconds 1.5\n"));
+        assert!(rendered.contains("ghso_github_api_requests_total 3\n"));
+        assert!(rendered.contains("ghso_github_rate_limit_remaining 4997\n"));
+        assert!(!rendered.contains("ghso_secret_changes"));
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let mut metrics = Metrics::new();
+        metrics.record_secret("org/repo", "deploy", "deploy_key", &ValidatorState::Expired, 200);
+        metrics.record_secret("org/repo", "deploy", "deploy_key", &ValidatorState::NotExpired, 10);
+        let rendered = metrics.render();
+        assert_eq!(rendered.matches("ghso_secret_age_days{").count(), 1);
+        assert!(rendered.contains("ghso_secret_age_days{repo=\"org/repo\",secret=\"deploy\",kind=\"deploy_key\"} 200\n"));
+        assert!(rendered.contains("ghso_secrets_total{state=\"not_expired\"} 1\n"));
+    }
+
+    #[test]
+    fn test_render_changes() {
+        let mut metrics = Metrics::new();
+        metrics.record_changes(&ChangeReport::default());
+        let rendered = metrics.render();
+        assert!(rendered.contains("ghso_secret_changes{change=\"rotated\"} 0\n"));
+        assert!(rendered.contains("ghso_secret_changes{change=\"deleted\"} 0\n"));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
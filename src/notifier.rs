@@ -1,34 +1,211 @@
-use anyhow::Error;
+mod dedup;
+mod issues;
+mod opsgenie;
+mod pagerduty;
+mod template;
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+
+use anyhow::{Context, Error};
 use tracing::{info, warn, error};
 use reqwest::{Client, Response};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 
+use crate::audit::{findings_state, Finding};
+use crate::changes::ChangeReport;
+use crate::observer::RepositoryFailure;
 use crate::validator::{ValidatorResult, ValidatorState};
+use crate::workflows::UsageReport;
 use crate::config::{NotifierConfig, NotifierType};
-use crate::github_api::{GitHubAPIRepository, GitHubAPISecret};
+use crate::github_api::{GitHubAPI, GitHubAPIRepository, GitHubAPISecret};
+use dedup::Deduplicator;
+use issues::IssueNotifier;
+use opsgenie::OpsgenieNotifier;
+use pagerduty::PagerDutyNotifier;
+use template::{DigestContext, MessageContext};
+pub use template::{InvalidTemplate, MessageTemplates};
 
 
 #[async_trait]
-pub trait NotifiactionService {
+pub trait NotifiactionService: Send + Sync {
+    /// Name used to look up the templates of the service in the configuration.
+    fn name(&self) -> &'static str;
+
     async fn send_msg(&self, msg: &str, validator_state: &ValidatorState) -> Result<(), Error>;
+
+    /// Whether unchanged results are filtered by the notification state. Services that
+    /// aggregate all results of a run opt out.
+    fn deduplicate(&self) -> bool {
+        true
+    }
+
+    /// Receives the validated secret next to the formatted message. Services that
+    /// need more context than the message itself override this.
+    async fn send_result(
+        &mut self,
+        msg: &str,
+        result: &ValidatorResult,
+        _secret: &GitHubAPISecret,
+        _repository: &GitHubAPIRepository
+    ) -> Result<(), Error> {
+        self.send_msg(msg, &result.state).await
+    }
+
+    /// Called with the secrets of every repository that was read, even if it has none.
+    async fn send_scanned(&mut self, _repository: &GitHubAPIRepository, _secrets: &[GitHubAPISecret]) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Open on-call alerts, which are kept in the notification state between runs.
+    /// `None` for services that don't raise alerts.
+    fn open_alerts(&self) -> Option<&OpenAlerts> {
+        None
+    }
+
+    /// Restores the open alerts of a previous run.
+    fn restore_alerts(&mut self, _alerts: OpenAlerts) {}
+
+    /// Receives the repositories that could not be scanned in this run.
+    async fn send_failures(&mut self, msg: &str, _failures: &[RepositoryFailure]) -> Result<(), Error> {
+        // Failures are warnings, as the findings of these repositories are unknown.
+        self.send_msg(msg, &ValidatorState::ExpiresSoon).await
+    }
+
+    /// Receives the findings of the configuration audits.
+    async fn send_findings(&mut self, msg: &str, findings: &[Finding]) -> Result<(), Error> {
+        self.send_digest(msg, &findings_state(findings)).await
+    }
+
+    /// Receives per run summaries, i.e. the digest and the change report.
+    async fn send_digest(&mut self, msg: &str, validator_state: &ValidatorState) -> Result<(), Error> {
+        self.send_msg(msg, validator_state).await
+    }
+
+    /// Called once after all repositories have been scanned.
+    async fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Drops what was collected for a run that did not reach `flush`, e.g. because
+    /// it failed. Called before every run.
+    fn reset(&mut self) {}
 }
 
-pub struct Notifier {
+/// Key used to deduplicate on-call alerts for a secret across runs.
+fn alert_key(secret: &GitHubAPISecret, repository: &GitHubAPIRepository) -> String {
+    format!("ghso:{}:{}", repository.full_name, secret.name)
+}
+
+/// On-call alerts raised by a service, by alert key. Only these are resolved, so
+/// secrets that never alerted don't cause requests.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpenAlerts {
+    /// Alert key to repository.
+    alerts: BTreeMap<String, String>,
+}
+
+impl OpenAlerts {
+    fn open(&mut self, key: String, repository: &GitHubAPIRepository) {
+        self.alerts.insert(key, repository.full_name.clone());
+    }
+
+    fn close(&mut self, key: &str) {
+        self.alerts.remove(key);
+    }
+
+    fn is_open(&self, key: &str) -> bool {
+        self.alerts.contains_key(key)
+    }
+
+    /// Open alerts of the repository whose secret was deleted.
+    fn vanished(&self, repository: &GitHubAPIRepository, secrets: &[GitHubAPISecret]) -> Vec<String> {
+        let current: HashSet<String> = secrets.iter().map(|secret| alert_key(secret, repository)).collect();
+        self.alerts.iter()
+            .filter(|(key, alert_repository)| **alert_repository == repository.full_name && !current.contains(*key))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+}
+
+/// Returns true if an expired secret is overdue long enough to page someone.
+fn requires_alert(result: &ValidatorResult, alert_overdue_days: i64) -> bool {
+    result.state == ValidatorState::Expired && result.days_overdue >= alert_overdue_days
+}
+
+struct NotifierService {
     service: Box<dyn NotifiactionService>,
+    templates: MessageTemplates,
+}
+
+pub struct Notifier {
+    services: Vec<NotifierService>,
+    /// Results of the current run, only collected when a digest is configured.
+    results: Vec<MessageContext>,
+    failures: Vec<RepositoryFailure>,
+    dedup: Option<Deduplicator>,
 }
 
 impl Notifier {
-    pub async fn new(config: NotifierConfig) -> Result<Self, Error> {
-        let service = Notifier::get_notifier_service(&config).await?;
+    pub async fn new(config: NotifierConfig, github_api: GitHubAPI) -> Result<Self, Error> {
+        let mut services = vec![Notifier::get_notifier_service(&config, github_api).await?];
+        services.extend(Notifier::get_alerting_services(&config));
+        let mut services = services
+            .into_iter()
+            .map(|service| {
+                let template_config = config.templates
+                    .as_ref()
+                    .and_then(|templates| templates.get(service.name()));
+                let templates = MessageTemplates::new(template_config)
+                    .with_context(|| format!("Failed to load templates of the {} notifier", service.name()))?;
+                Ok(NotifierService { service, templates })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let dedup = if config.deduplicate || config.state_file.is_some() {
+            Some(Deduplicator::new(
+                config.state_file.as_ref().map(|state_file| state_file.into()),
+                config.reminder_expired_days,
+                config.reminder_expires_soon_days
+            )?)
+        } else {
+            None
+        };
+        if let Some(dedup) = &dedup {
+            for entry in services.iter_mut() {
+                entry.service.restore_alerts(dedup.alerts(entry.service.name()));
+            }
+        }
         Ok(Notifier {
-            service
+            services,
+            results: Vec::new(),
+            failures: Vec::new(),
+            dedup,
         })
     }
 
-    async fn get_notifier_service(config: &NotifierConfig) -> Result<Box<dyn NotifiactionService>, Error> {
+    /// On-call alerting runs next to the configured notifier and only fires for
+    /// secrets that are overdue beyond `alert_overdue_days`.
+    fn get_alerting_services(config: &NotifierConfig) -> Vec<Box<dyn NotifiactionService>> {
+        let mut services: Vec<Box<dyn NotifiactionService>> = Vec::new();
+        if let Some(routing_key) = config.pagerduty_routing_key.clone() {
+            services.push(Box::new(PagerDutyNotifier::new(routing_key, config.alert_overdue_days)));
+        }
+        if let Some(api_key) = config.opsgenie_api_key.clone() {
+            services.push(Box::new(OpsgenieNotifier::new(
+                api_key,
+                config.opsgenie_url.clone(),
+                config.alert_overdue_days
+            )));
+        }
+        services
+    }
+
+    async fn get_notifier_service(config: &NotifierConfig, github_api: GitHubAPI) -> Result<Box<dyn NotifiactionService>, Error> {
         match config.notifier_type {
             NotifierType::Log => Ok(Box::new(LogNotifier::new())),
             NotifierType::GitHub => Ok(Box::new(GitHubNotifier::new())),
+            NotifierType::Issues => Ok(Box::new(IssueNotifier::new(config, github_api))),
             NotifierType::Slack => {
                 if let Some(webhook_url) = config.slack_webhook.clone() {
                     Ok(Box::new(SlackNotifier::new(webhook_url)))
@@ -40,32 +217,105 @@ impl Notifier {
     }
 
     pub async fn notify(&mut self, result: &ValidatorResult, secret: &GitHubAPISecret, repository: &GitHubAPIRepository) -> Result<(), Error> {
+        let context = MessageContext::new(result, secret, repository);
+        let notify = match self.dedup.as_mut() {
+            Some(dedup) => dedup.should_notify(&repository.full_name, &secret.name, result.state, chrono::Utc::now()),
+            None => true,
+        };
+        for entry in self.services.iter_mut() {
+            if !notify && entry.service.deduplicate() {
+                continue;
+            }
+            let msg = entry.templates.render_message(&context)?;
+            entry.service.send_result(&msg, result, secret, repository).await?;
+        }
+        if self.services.iter().any(|entry| entry.templates.has_digest()) {
+            self.results.push(context);
+        }
+        Ok(())
+    }
 
-        let mut msg = format!(
-            "[state={:?}, name={}, repository={}, days_age={}, days_left={}, days_overdue={}]",
-            result.state, secret.name, &repository.full_name, result.days_age, result.days_left, result.days_overdue
-        );
+    /// Starts a new run, dropping the state left behind by a run that failed.
+    pub fn reset(&mut self) {
+        self.results.clear();
+        self.failures.clear();
+        for entry in self.services.iter_mut() {
+            entry.service.reset();
+        }
+        if let Some(dedup) = self.dedup.as_mut() {
+            dedup.reset();
+        }
+    }
 
-        match result.state {
-            ValidatorState::Expired => {
-                msg = format!("❌ {} Secret is expired.", msg);
-            },
-            ValidatorState::NotExpired => {
-                msg = format!("✅ {} Secret is not expired.", msg);
-            },
-            ValidatorState::Ignored => {
-                msg = format!("🤷 {} Secret is ignored.", msg);
-            },
-            ValidatorState::ExpiresSoon => {
-                msg = format!("⚠️ {} Secret expires soon.", msg);
+    /// Tells every service which secrets the repository has.
+    pub async fn report_scanned(&mut self, repository: &GitHubAPIRepository, secrets: &[GitHubAPISecret]) -> Result<(), Error> {
+        for entry in self.services.iter_mut() {
+            entry.service.send_scanned(repository, secrets).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends the repositories that could not be scanned to every service. They are
+    /// also part of the digest.
+    pub async fn report_failures(&mut self, failures: &[RepositoryFailure]) -> Result<(), Error> {
+        for entry in self.services.iter_mut() {
+            let msg = entry.templates.render_failures(failures)?;
+            entry.service.send_failures(&msg, failures).await?;
+        }
+        self.failures = failures.to_vec();
+        Ok(())
+    }
+
+    /// Sends the differences to the previous run to every service.
+    pub async fn report_changes(&mut self, report: &ChangeReport) -> Result<(), Error> {
+        for entry in self.services.iter_mut() {
+            let msg = entry.templates.render_changes(report)?;
+            entry.service.send_digest(&msg, &report.state()).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends the unused and missing secrets to every service.
+    pub async fn report_usage(&mut self, report: &UsageReport) -> Result<(), Error> {
+        for entry in self.services.iter_mut() {
+            let msg = entry.templates.render_usage(report)?;
+            // Unused and missing secrets are warnings, they don't affect the rotation.
+            entry.service.send_digest(&msg, &ValidatorState::ExpiresSoon).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends the findings of the configuration audits to every service.
+    pub async fn report_findings(&mut self, findings: &[Finding]) -> Result<(), Error> {
+        for entry in self.services.iter_mut() {
+            let msg = entry.templates.render_findings(findings)?;
+            entry.service.send_findings(&msg, findings).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> Result<(), Error> {
+        let results = std::mem::take(&mut self.results);
+        let failures = std::mem::take(&mut self.failures);
+        let digest = DigestContext::new(&results, &failures);
+        for entry in self.services.iter_mut() {
+            if let Some(msg) = entry.templates.render_digest(&digest)? {
+                entry.service.send_digest(&msg, &digest.state()).await?;
             }
-        };
-        self.service.send_msg(&msg, &result.state).await?;
+            entry.service.flush().await?;
+        }
+        if let Some(dedup) = self.dedup.as_mut() {
+            for entry in self.services.iter() {
+                if let Some(alerts) = entry.service.open_alerts() {
+                    dedup.set_alerts(entry.service.name(), alerts.clone());
+                }
+            }
+            dedup.save()?;
+        }
         Ok(())
     }
 }
 
-
 pub struct LogNotifier;
 
 impl LogNotifier {
@@ -76,6 +326,10 @@ impl LogNotifier {
 
 #[async_trait]
 impl NotifiactionService for LogNotifier {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
     async fn send_msg(&self, msg: &str, validator_state: &ValidatorState) -> Result<(), Error> {
         match validator_state {
             ValidatorState::Expired => {
@@ -95,33 +349,168 @@ impl NotifiactionService for LogNotifier {
     }
 }
 
-pub struct GitHubNotifier;
+/// Mentions of the owners in the step summary, `-` if there are none.
+fn owners(owners: &[String]) -> String {
+    if owners.is_empty() {
+        return "-".to_string();
+    }
+    owners.iter().map(|owner| format!("@{}", owner)).collect::<Vec<_>>().join(", ")
+}
+
+/// Escapes data of a workflow command as required by the GitHub Actions runner.
+fn escape_workflow_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a workflow command property, which additionally must not contain `:` or `,`.
+fn escape_workflow_property(value: &str) -> String {
+    escape_workflow_data(value)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+struct SummaryRow {
+    repository: String,
+    secret: String,
+    result: ValidatorResult,
+    owner: Option<String>,
+}
+
+pub struct GitHubNotifier {
+    summary_rows: Vec<SummaryRow>,
+    failures: Vec<RepositoryFailure>,
+    findings: Vec<Finding>,
+}
 
 impl GitHubNotifier {
     pub fn new() -> Self {
-        GitHubNotifier
+        GitHubNotifier {
+            summary_rows: Vec::new(),
+            failures: Vec::new(),
+            findings: Vec::new(),
+        }
+    }
+
+    fn workflow_command(msg: &str, validator_state: &ValidatorState) -> String {
+        let (command, title) = match validator_state {
+            ValidatorState::Expired => ("error", "Secret expired"),
+            ValidatorState::ExpiresSoon => ("warning", "Secret expires soon"),
+            ValidatorState::Ignored => ("notice", "Secret ignored"),
+            ValidatorState::NotExpired => return msg.to_string(),
+        };
+        format!("::{} title={}::{}", command, escape_workflow_property(title), escape_workflow_data(msg))
+    }
+
+    fn render_summary(rows: &[SummaryRow], failures: &[RepositoryFailure], findings: &[Finding]) -> String {
+        let mut summary = String::from(
+            "## GitHub Secrets Observer\n\n\
+            | Repository | Secret | State | Severity | Age (days) | Days left | Days overdue | Owner |\n\
+            | ---------- | ------ | ----- | -------- | ---------- | --------- | ------------ | ----- |\n"
+        );
+        for row in rows {
+            summary.push_str(&format!(
+                "| {} | `{}` | {:?} | {:?} | {} | {} | {} | {} |\n",
+                row.repository, row.secret, row.result.state, row.result.severity,
+                row.result.days_age, row.result.days_left, row.result.days_overdue,
+                owners(row.owner.as_slice())
+            ));
+        }
+        if !failures.is_empty() {
+            summary.push_str(
+                "\n### Repositories not scanned\n\n\
+                | Repository | Reason |\n\
+                | ---------- | ------ |\n"
+            );
+            for failure in failures {
+                summary.push_str(&format!("| {} | {} |\n", failure.repository, failure.reason.replace('|', "\\|")));
+            }
+        }
+        if !findings.is_empty() {
+            summary.push_str(
+                "\n### Audit findings\n\n\
+                | Severity | Target | Subject | Finding | Owner |\n\
+                | -------- | ------ | ------- | ------- | ----- |\n"
+            );
+            for finding in findings {
+                summary.push_str(&format!(
+                    "| {:?} | {} | `{}` | {} | {} |\n",
+                    finding.severity, finding.target, finding.subject, finding.message.replace('|', "\\|"),
+                    owners(&finding.owners)
+                ));
+            }
+        }
+        summary
     }
 }
 
 #[async_trait]
 impl NotifiactionService for GitHubNotifier {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn deduplicate(&self) -> bool {
+        false
+    }
+
     async fn send_msg(&self, msg: &str, validator_state: &ValidatorState) -> Result<(), Error> {
-        match validator_state {
-            ValidatorState::Expired => {
-                println!("::error::{}", msg);
-            },
-            ValidatorState::NotExpired => {
-                println!("{}", msg);
-            },
-            ValidatorState::Ignored => {
-                println!("::info::{}", msg);
-            },
-            ValidatorState::ExpiresSoon => {
-                println!("::warn::{}", msg);
-            }
-        }
+        println!("{}", GitHubNotifier::workflow_command(msg, validator_state));
+        Ok(())
+    }
+
+    async fn send_result(
+        &mut self,
+        msg: &str,
+        result: &ValidatorResult,
+        secret: &GitHubAPISecret,
+        repository: &GitHubAPIRepository
+    ) -> Result<(), Error> {
+        self.send_msg(msg, &result.state).await?;
+        self.summary_rows.push(SummaryRow {
+            repository: repository.full_name.clone(),
+            secret: secret.name.clone(),
+            result: result.clone(),
+            owner: result.owner().map(str::to_string),
+        });
+        Ok(())
+    }
+
+    async fn send_failures(&mut self, msg: &str, failures: &[RepositoryFailure]) -> Result<(), Error> {
+        self.send_msg(msg, &ValidatorState::ExpiresSoon).await?;
+        self.failures = failures.to_vec();
+        Ok(())
+    }
+
+    async fn send_findings(&mut self, msg: &str, findings: &[Finding]) -> Result<(), Error> {
+        self.send_msg(msg, &findings_state(findings)).await?;
+        self.findings = findings.to_vec();
         Ok(())
     }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        let rows = std::mem::take(&mut self.summary_rows);
+        let failures = std::mem::take(&mut self.failures);
+        let findings = std::mem::take(&mut self.findings);
+        // The summary file is only provided when running inside a workflow step.
+        let Ok(path) = std::env::var("GITHUB_STEP_SUMMARY") else {
+            return Ok(());
+        };
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        file.write_all(GitHubNotifier::render_summary(&rows, &failures, &findings).as_bytes())?;
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.summary_rows.clear();
+        self.failures.clear();
+        self.findings.clear();
+    }
 }
 
 pub struct SlackNotifier  {
@@ -152,8 +541,126 @@ impl SlackNotifier {
 
 #[async_trait]
 impl NotifiactionService for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
     async fn send_msg(&self, msg: &str, _: &ValidatorState) -> Result<(), Error> {
         self.send_slack_msg(msg).await?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::FindingKind;
+    use crate::validator::Severity;
+    use crate::workflows::Exposure;
+
+    fn result(state: ValidatorState, days_overdue: i64) -> ValidatorResult {
+        ValidatorResult {
+            state,
+            days_age: 0,
+            days_left: 0,
+            days_overdue,
+            exposure: Exposure::Trusted,
+            severity: Severity::new(state, Exposure::Trusted),
+            updated_by: None,
+        }
+    }
+
+    #[test]
+    fn test_workflow_command() {
+        assert_eq!(
+            GitHubNotifier::workflow_command("100% expired\nsoon", &ValidatorState::Expired),
+            "::error title=Secret expired::100%25 expired%0Asoon"
+        );
+        assert_eq!(
+            GitHubNotifier::workflow_command("msg", &ValidatorState::ExpiresSoon),
+            "::warning title=Secret expires soon::msg"
+        );
+        assert_eq!(
+            GitHubNotifier::workflow_command("msg", &ValidatorState::Ignored),
+            "::notice title=Secret ignored::msg"
+        );
+        assert_eq!(GitHubNotifier::workflow_command("msg", &ValidatorState::NotExpired), "msg");
+    }
+
+    #[test]
+    fn test_escape_workflow_property() {
+        assert_eq!(escape_workflow_property("a:b,c\r"), "a%3Ab%2Cc%0D");
+    }
+
+    #[test]
+    fn test_render_summary() {
+        let rows = vec![SummaryRow {
+            repository: "my_org/my_repo".to_string(),
+            secret: "SECRET".to_string(),
+            result: result(ValidatorState::Expired, 3),
+            owner: Some("octocat".to_string()),
+        }];
+        let summary = GitHubNotifier::render_summary(&rows, &[], &[]);
+        assert!(summary.contains("| Repository | Secret | State | Severity | Age (days) | Days left | Days overdue | Owner |\n"));
+        assert!(summary.ends_with("| my_org/my_repo | `SECRET` | Expired | High | 0 | 0 | 3 | @octocat |\n"));
+
+        let failures = vec![RepositoryFailure {
+            repository: "my_org/other".to_string(),
+            reason: "Not Found".to_string(),
+        }];
+        let summary = GitHubNotifier::render_summary(&rows, &failures, &[]);
+        assert!(summary.contains("### Repositories not scanned"));
+        assert!(summary.ends_with("| my_org/other | Not Found |\n"));
+
+        let findings = vec![Finding {
+            kind: FindingKind::OrganizationSecretVisibleToAll,
+            severity: Severity::High,
+            target: "my_org".to_string(),
+            subject: "NPM_TOKEN".to_string(),
+            message: "Secret is available to all repositories.".to_string(),
+            owners: vec!["octocat".to_string()],
+        }];
+        let summary = GitHubNotifier::render_summary(&rows, &[], &findings);
+        assert!(summary.contains("### Audit findings"));
+        assert!(summary.ends_with("| High | my_org | `NPM_TOKEN` | Secret is available to all repositories. | @octocat |\n"));
+    }
+
+    #[test]
+    fn test_vanished_alerts() {
+        let repository: GitHubAPIRepository = serde_json::from_value(serde_json::json!({
+            "id": 1, "name": "my_repo", "full_name": "my_org/my_repo"
+        })).unwrap();
+        let other: GitHubAPIRepository = serde_json::from_value(serde_json::json!({
+            "id": 2, "name": "other", "full_name": "my_org/other"
+        })).unwrap();
+        let secret = |name: &str| GitHubAPISecret { name: name.to_string(), ..Default::default() };
+
+        let mut alerts = OpenAlerts::default();
+        alerts.open(alert_key(&secret("KEPT"), &repository), &repository);
+        alerts.open(alert_key(&secret("DELETED"), &repository), &repository);
+        alerts.open(alert_key(&secret("OTHER"), &other), &other);
+        assert_eq!(alerts.vanished(&repository, &[secret("KEPT")]), vec!["ghso:my_org/my_repo:DELETED"]);
+        alerts.close("ghso:my_org/my_repo:DELETED");
+        assert!(!alerts.is_open("ghso:my_org/my_repo:DELETED"));
+        assert!(alerts.is_open("ghso:my_org/other:OTHER"));
+    }
+
+    #[tokio::test]
+    async fn test_reset_drops_results_of_failed_run() {
+        let mut notifier = GitHubNotifier::new();
+        let repository: GitHubAPIRepository = serde_json::from_value(serde_json::json!({
+            "id": 1, "name": "my_repo", "full_name": "my_org/my_repo"
+        })).unwrap();
+        let secret = GitHubAPISecret { name: "SECRET".to_string(), ..Default::default() };
+        notifier.send_result("msg", &result(ValidatorState::Expired, 3), &secret, &repository).await.unwrap();
+        assert_eq!(notifier.summary_rows.len(), 1);
+        notifier.reset();
+        assert!(notifier.summary_rows.is_empty());
+    }
+
+    #[test]
+    fn test_requires_alert() {
+        assert!(requires_alert(&result(ValidatorState::Expired, 30), 30));
+        assert!(!requires_alert(&result(ValidatorState::Expired, 29), 30));
+        assert!(!requires_alert(&result(ValidatorState::ExpiresSoon, 0), 0));
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use anyhow::{Context, Error};
+use chrono::prelude::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::validator::ValidatorState;
+use super::OpenAlerts;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NotificationRecord {
+    state: ValidatorState,
+    notified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct NotificationState {
+    records: HashMap<String, NotificationRecord>,
+    /// Open on-call alerts by service name.
+    #[serde(default)]
+    alerts: HashMap<String, OpenAlerts>,
+}
+
+/// Remembers which secrets were notified in which state, so unchanged secrets are
+/// only re-notified after their reminder interval.
+pub struct Deduplicator {
+    path: Option<PathBuf>,
+    state: NotificationState,
+    reminder_expired_days: i64,
+    reminder_expires_soon_days: i64,
+    seen_repositories: HashSet<String>,
+    seen_keys: HashSet<String>,
+    /// Notifications of the current run, only recorded by `save` once the run
+    /// was flushed, so a failed send is retried by the next run.
+    staged: HashMap<String, NotificationRecord>,
+}
+
+impl Deduplicator {
+    /// Loads the state from `path` if the file exists. Without a path the state is
+    /// only kept in memory.
+    pub fn new(path: Option<PathBuf>, reminder_expired_days: i64, reminder_expires_soon_days: i64) -> Result<Self, Error> {
+        let state = match &path {
+            Some(path) if path.exists() => {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read state file {}", path.display()))?;
+                serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse state file {}", path.display()))?
+            },
+            _ => NotificationState::default(),
+        };
+        Ok(Deduplicator {
+            path,
+            state,
+            reminder_expired_days,
+            reminder_expires_soon_days,
+            seen_repositories: HashSet::new(),
+            seen_keys: HashSet::new(),
+            staged: HashMap::new(),
+        })
+    }
+
+    fn key(repository: &str, secret: &str) -> String {
+        format!("{}/{}", repository, secret)
+    }
+
+    fn reminder_days(&self, state: &ValidatorState) -> Option<i64> {
+        match state {
+            ValidatorState::Expired => Some(self.reminder_expired_days),
+            ValidatorState::ExpiresSoon => Some(self.reminder_expires_soon_days),
+            ValidatorState::NotExpired | ValidatorState::Ignored => None,
+        }
+    }
+
+    /// Returns true if the secret must be notified and records the notification.
+    pub fn should_notify(&mut self, repository: &str, secret: &str, state: ValidatorState, now: DateTime<Utc>) -> bool {
+        let key = Deduplicator::key(repository, secret);
+        self.seen_repositories.insert(repository.to_string());
+        self.seen_keys.insert(key.clone());
+
+        let notify = match self.staged.get(&key).or_else(|| self.state.records.get(&key)) {
+            None => true,
+            Some(record) if record.state != state => true,
+            Some(record) => match self.reminder_days(&state) {
+                // Calendar days are compared, so a daily run is not skipped because
+                // it started a few minutes earlier than the day before.
+                Some(days) => (now.date_naive() - record.notified_at.date_naive()).num_days() >= days,
+                None => false,
+            },
+        };
+        if notify {
+            self.staged.insert(key, NotificationRecord { state, notified_at: now });
+        } else {
+            debug!("Skipping notification for {} in {}, already notified.", secret, repository);
+        }
+        notify
+    }
+
+    /// Forgets the secrets seen and notified by a run that was not saved.
+    pub fn reset(&mut self) {
+        self.seen_repositories.clear();
+        self.seen_keys.clear();
+        self.staged.clear();
+    }
+
+    pub fn alerts(&self, service: &str) -> OpenAlerts {
+        self.state.alerts.get(service).cloned().unwrap_or_default()
+    }
+
+    pub fn set_alerts(&mut self, service: &str, alerts: OpenAlerts) {
+        self.state.alerts.insert(service.to_string(), alerts);
+    }
+
+    /// Records the notifications of the run, forgets secrets that disappeared from
+    /// scanned repositories and writes the state file, if configured.
+    pub fn save(&mut self) -> Result<(), Error> {
+        self.state.records.extend(std::mem::take(&mut self.staged));
+        let seen_repositories = std::mem::take(&mut self.seen_repositories);
+        let seen_keys = std::mem::take(&mut self.seen_keys);
+        self.state.records.retain(|key, _| {
+            let repository = key.rsplit_once('/').map(|(repository, _)| repository).unwrap_or_default();
+            seen_keys.contains(key) || !seen_repositories.contains(repository)
+        });
+
+        if let Some(path) = &self.path {
+            let content = serde_json::to_string_pretty(&self.state)?;
+            std::fs::write(path, content)
+                .with_context(|| format!("Failed to write state file {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_notify_on_state_change() {
+        let mut dedup = Deduplicator::new(None, 1, 7).unwrap();
+        let now = Utc::now();
+        assert!(dedup.should_notify("org/repo", "SECRET", ValidatorState::NotExpired, now));
+        assert!(!dedup.should_notify("org/repo", "SECRET", ValidatorState::NotExpired, now + Duration::days(30)));
+        assert!(dedup.should_notify("org/repo", "SECRET", ValidatorState::ExpiresSoon, now + Duration::days(30)));
+    }
+
+    #[test]
+    fn test_reminder_interval() {
+        let mut dedup = Deduplicator::new(None, 1, 7).unwrap();
+        let now = Utc::now();
+        assert!(dedup.should_notify("org/repo", "SECRET", ValidatorState::ExpiresSoon, now));
+        assert!(!dedup.should_notify("org/repo", "SECRET", ValidatorState::ExpiresSoon, now + Duration::days(6)));
+        assert!(dedup.should_notify("org/repo", "SECRET", ValidatorState::ExpiresSoon, now + Duration::days(7)));
+        assert!(dedup.should_notify("org/repo", "SECRET", ValidatorState::Expired, now + Duration::days(8)));
+        assert!(dedup.should_notify("org/repo", "SECRET", ValidatorState::Expired, now + Duration::days(9)));
+    }
+
+    #[test]
+    fn test_failed_run_notifies_again() {
+        let mut dedup = Deduplicator::new(None, 1, 7).unwrap();
+        let now = Utc::now();
+        assert!(dedup.should_notify("org/repo", "SECRET", ValidatorState::Expired, now));
+        // Sending failed, so the run was neither flushed nor saved.
+        dedup.reset();
+        assert!(dedup.should_notify("org/repo", "SECRET", ValidatorState::Expired, now));
+        dedup.save().unwrap();
+        assert!(!dedup.should_notify("org/repo", "SECRET", ValidatorState::Expired, now));
+    }
+
+    #[test]
+    fn test_save_forgets_deleted_secrets() {
+        let mut dedup = Deduplicator::new(None, 1, 7).unwrap();
+        let now = Utc::now();
+        dedup.should_notify("org/repo", "DELETED", ValidatorState::Expired, now);
+        dedup.should_notify("org/other", "SECRET", ValidatorState::Expired, now);
+        dedup.save().unwrap();
+        dedup.should_notify("org/repo", "NEW", ValidatorState::Expired, now);
+        dedup.save().unwrap();
+        assert!(!dedup.state.records.contains_key("org/repo/DELETED"));
+        assert!(dedup.state.records.contains_key("org/other/SECRET"));
+    }
+}
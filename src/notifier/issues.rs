@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::Error;
+use async_trait::async_trait;
+use tracing::{info, warn};
+
+use crate::config::{IssueGrouping, NotifierConfig};
+use crate::github_api::{GitHubAPI, GitHubAPIIssue, GitHubAPIIssueRequest, GitHubAPIRepository, GitHubAPISecret};
+use crate::validator::{ValidatorResult, ValidatorState};
+use super::NotifiactionService;
+
+const DEFAULT_LABEL: &str = "secrets-observer";
+const MARKER_PREFIX: &str = "<!-- ghso-issue:";
+
+/// Identifies the issue a finding belongs to. The secret is only set when issues
+/// are grouped per secret.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct IssueKey {
+    repository: String,
+    secret: Option<String>,
+}
+
+impl IssueKey {
+    fn marker(&self) -> String {
+        match &self.secret {
+            Some(secret) => format!("{} {}/{} -->", MARKER_PREFIX, self.repository, secret),
+            None => format!("{} {} -->", MARKER_PREFIX, self.repository),
+        }
+    }
+
+    /// Restores the key from the hidden marker in an issue body.
+    fn from_body(body: &str) -> Option<IssueKey> {
+        let start = body.find(MARKER_PREFIX)? + MARKER_PREFIX.len();
+        let end = start + body[start..].find("-->")?;
+        let value = body[start..end].trim();
+        let mut parts = value.splitn(3, '/');
+        let owner = parts.next()?;
+        let name = parts.next()?;
+        Some(IssueKey {
+            repository: format!("{}/{}", owner, name),
+            secret: parts.next().map(|s| s.to_string()),
+        })
+    }
+
+    fn title(&self) -> String {
+        match &self.secret {
+            Some(secret) => format!("Secret rotation required: {} in {}", secret, self.repository),
+            None => format!("Secret rotation required in {}", self.repository),
+        }
+    }
+}
+
+struct IssueFinding {
+    secret: String,
+    result: ValidatorResult,
+    owner: Option<String>,
+}
+
+pub struct IssueNotifier {
+    github_api: GitHubAPI,
+    tracking_repository: Option<String>,
+    grouping: IssueGrouping,
+    label: String,
+    assignees: Vec<String>,
+    findings: BTreeMap<IssueKey, Vec<IssueFinding>>,
+    /// Repositories whose secrets were read in this run.
+    scanned: HashSet<String>,
+}
+
+impl IssueNotifier {
+    pub fn new(config: &NotifierConfig, github_api: GitHubAPI) -> Self {
+        IssueNotifier {
+            github_api,
+            tracking_repository: config.issue_repository.clone(),
+            grouping: config.issue_grouping,
+            label: config.issue_label.clone().unwrap_or_else(|| DEFAULT_LABEL.to_string()),
+            assignees: config.issue_assignees.clone().unwrap_or_default(),
+            findings: BTreeMap::new(),
+            scanned: HashSet::new(),
+        }
+    }
+
+    fn key(&self, secret: &GitHubAPISecret, repository: &GitHubAPIRepository) -> IssueKey {
+        IssueKey {
+            repository: repository.full_name.clone(),
+            secret: match self.grouping {
+                IssueGrouping::Repository => None,
+                IssueGrouping::Secret => Some(secret.name.clone()),
+            },
+        }
+    }
+
+    /// Repository the issues of the given repository are maintained in.
+    fn target_repository<'a>(&'a self, repository: &'a str) -> &'a str {
+        self.tracking_repository.as_deref().unwrap_or(repository)
+    }
+
+    fn state_label(&self, state: &ValidatorState) -> Option<String> {
+        match state {
+            ValidatorState::Expired => Some(format!("{}:expired", self.label)),
+            ValidatorState::ExpiresSoon => Some(format!("{}:expires-soon", self.label)),
+            _ => None,
+        }
+    }
+
+    fn labels(&self, findings: &[IssueFinding]) -> Vec<String> {
+        let mut labels = vec![self.label.clone()];
+        for finding in findings {
+            if let Some(label) = self.state_label(&finding.result.state) {
+                if !labels.contains(&label) {
+                    labels.push(label);
+                }
+            }
+        }
+        labels
+    }
+
+    /// The configured assignees, or else the owners of the secrets.
+    fn assignees(&self, findings: &[IssueFinding]) -> Vec<String> {
+        if !self.assignees.is_empty() {
+            return self.assignees.clone();
+        }
+        let mut owners: Vec<String> = findings.iter().filter_map(|finding| finding.owner.clone()).collect();
+        owners.sort();
+        owners.dedup();
+        owners
+    }
+
+    fn render_body(key: &IssueKey, findings: &[IssueFinding]) -> String {
+        let mut body = format!(
+            "The following secrets in `{}` need to be rotated.\n\n\
+            | Secret | State | Age (days) | Days left | Days overdue | Last updated by |\n\
+            | ------ | ----- | ---------- | --------- | ------------ | --------------- |\n",
+            key.repository
+        );
+        for finding in findings {
+            body.push_str(&format!(
+                "| `{}` | {:?} | {} | {} | {} | {} |\n",
+                finding.secret,
+                finding.result.state,
+                finding.result.days_age,
+                finding.result.days_left,
+                finding.result.days_overdue,
+                finding.owner.as_deref().map(|owner| format!("@{}", owner)).unwrap_or_else(|| "-".to_string())
+            ));
+        }
+        body.push_str(
+            "\nThis issue is maintained by GitHub Secrets Observer and is closed automatically \
+            once the secrets have been rotated.\n\n"
+        );
+        body.push_str(&key.marker());
+        body
+    }
+
+    async fn open_issues(&self, repository: &str) -> Result<Vec<(IssueKey, GitHubAPIIssue)>, Error> {
+        let issues = self.github_api.get_open_issues(repository, &self.label).await?;
+        Ok(issues
+            .into_iter()
+            .filter_map(|issue| {
+                let key = IssueKey::from_body(issue.body.as_deref().unwrap_or_default())?;
+                Some((key, issue))
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl NotifiactionService for IssueNotifier {
+    fn name(&self) -> &'static str {
+        "issues"
+    }
+
+    fn deduplicate(&self) -> bool {
+        false
+    }
+
+    async fn send_msg(&self, msg: &str, _: &ValidatorState) -> Result<(), Error> {
+        info!("{}", msg);
+        Ok(())
+    }
+
+    async fn send_result(
+        &mut self,
+        msg: &str,
+        result: &ValidatorResult,
+        secret: &GitHubAPISecret,
+        repository: &GitHubAPIRepository
+    ) -> Result<(), Error> {
+        self.send_msg(msg, &result.state).await?;
+        let key = self.key(secret, repository);
+        if matches!(result.state, ValidatorState::Expired | ValidatorState::ExpiresSoon) {
+            self.findings.entry(key).or_default().push(IssueFinding {
+                secret: secret.name.clone(),
+                result: result.clone(),
+                owner: result.owner().map(str::to_string),
+            });
+        }
+        Ok(())
+    }
+
+    async fn send_scanned(&mut self, repository: &GitHubAPIRepository, _secrets: &[GitHubAPISecret]) -> Result<(), Error> {
+        self.scanned.insert(repository.full_name.clone());
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        let targets: HashSet<String> = self.scanned
+            .iter()
+            .map(|repository| self.target_repository(repository).to_string())
+            .collect();
+
+        let mut existing: HashMap<IssueKey, GitHubAPIIssue> = HashMap::new();
+        for target in targets.iter() {
+            for (key, issue) in self.open_issues(target).await? {
+                existing.insert(key, issue);
+            }
+        }
+
+        for (key, findings) in self.findings.iter() {
+            let target = self.target_repository(&key.repository);
+            let request = GitHubAPIIssueRequest {
+                title: Some(key.title()),
+                body: Some(IssueNotifier::render_body(key, findings)),
+                labels: Some(self.labels(findings)),
+                assignees: Some(self.assignees(findings)),
+                ..Default::default()
+            };
+            match existing.get(key) {
+                Some(issue) => {
+                    info!("Updating issue #{} in {}.", issue.number, target);
+                    if let Err(e) = self.github_api.update_issue(target, issue.number, &request).await {
+                        warn!("Failed to update issue #{} in {}. Reason: {:?}", issue.number, target, e);
+                    }
+                },
+                None => match self.github_api.create_issue(target, &request).await {
+                    Ok(issue) => info!("Created issue #{} in {}.", issue.number, target),
+                    Err(e) => warn!("Failed to create issue \"{}\" in {}. Reason: {:?}", key.title(), target, e),
+                }
+            }
+        }
+
+        // Open issues without findings belong to secrets that were rotated or deleted.
+        // Only repositories scanned in this run are considered.
+        for (key, issue) in existing.iter() {
+            if self.findings.contains_key(key) {
+                continue;
+            }
+            if !self.scanned.contains(&key.repository) {
+                continue;
+            }
+            let target = self.target_repository(&key.repository);
+            info!("Closing issue #{} in {} as the secrets have been rotated.", issue.number, target);
+            let request = GitHubAPIIssueRequest {
+                state: Some("closed".to_string()),
+                state_reason: Some("completed".to_string()),
+                labels: Some(vec![self.label.clone()]),
+                ..Default::default()
+            };
+            if let Err(e) = self.github_api.update_issue(target, issue.number, &request).await {
+                warn!("Failed to close issue #{} in {}. Reason: {:?}", issue.number, target, e);
+            }
+        }
+
+        self.findings.clear();
+        self.scanned.clear();
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.findings.clear();
+        self.scanned.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marker_roundtrip_repository() {
+        let key = IssueKey { repository: "my_org/my_repo".to_string(), secret: None };
+        let body = format!("Some text\n\n{}", key.marker());
+        assert_eq!(IssueKey::from_body(&body), Some(key));
+    }
+
+    #[test]
+    fn test_marker_roundtrip_secret() {
+        let key = IssueKey {
+            repository: "my_org/my_repo".to_string(),
+            secret: Some("MY_SECRET_R30".to_string()),
+        };
+        assert_eq!(IssueKey::from_body(&key.marker()), Some(key));
+    }
+
+    #[test]
+    fn test_marker_missing() {
+        assert_eq!(IssueKey::from_body("An issue created by a human."), None);
+    }
+
+    #[test]
+    fn test_assignees_default_to_owners() {
+        let finding = |secret: &str, owner: Option<&str>| IssueFinding {
+            secret: secret.to_string(),
+            result: ValidatorResult {
+                state: ValidatorState::Expired,
+                days_age: 100,
+                days_left: 0,
+                days_overdue: 10,
+                exposure: crate::workflows::Exposure::Trusted,
+                severity: crate::validator::Severity::High,
+                updated_by: None,
+            },
+            owner: owner.map(str::to_string),
+        };
+        let findings = vec![finding("A", Some("octocat")), finding("B", None), finding("C", Some("octocat"))];
+        let mut notifier = IssueNotifier {
+            github_api: GitHubAPI::new(None, Some("token".to_string())).unwrap(),
+            tracking_repository: None,
+            grouping: IssueGrouping::Repository,
+            label: DEFAULT_LABEL.to_string(),
+            assignees: Vec::new(),
+            findings: BTreeMap::new(),
+            scanned: HashSet::new(),
+        };
+        assert_eq!(notifier.assignees(&findings), vec!["octocat"]);
+        assert!(IssueNotifier::render_body(&IssueKey { repository: "my_org/my_repo".to_string(), secret: None }, &findings)
+            .contains("| `B` | Expired | 100 | 0 | 10 | - |\n"));
+
+        notifier.assignees = vec!["security-team".to_string()];
+        assert_eq!(notifier.assignees(&findings), vec!["security-team"]);
+    }
+}
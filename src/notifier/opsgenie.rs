@@ -0,0 +1,158 @@
+use anyhow::{Context, Error};
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, Url};
+use serde_json::json;
+use tracing::info;
+
+use crate::github_api::{GitHubAPIRepository, GitHubAPISecret};
+use crate::validator::{Severity, ValidatorResult, ValidatorState};
+use super::{alert_key, requires_alert, NotifiactionService, OpenAlerts};
+
+const DEFAULT_URL: &str = "https://api.opsgenie.com";
+const SOURCE: &str = "github-secrets-observer";
+
+pub struct OpsgenieNotifier {
+    api_key: String,
+    url: String,
+    alert_overdue_days: i64,
+    client: Client,
+    alerts: OpenAlerts,
+}
+
+impl OpsgenieNotifier {
+    pub fn new(api_key: String, url: Option<String>, alert_overdue_days: i64) -> Self {
+        OpsgenieNotifier {
+            api_key,
+            url: url.unwrap_or_else(|| DEFAULT_URL.to_string()),
+            alert_overdue_days,
+            client: Client::new(),
+            alerts: OpenAlerts::default(),
+        }
+    }
+
+    fn post(&self, url: Url) -> RequestBuilder {
+        self.client.post(url)
+            .header("Authorization", format!("GenieKey {}", self.api_key))
+    }
+
+    /// Builds the URL from path segments, which are percent-encoded. The alias contains
+    /// the `/` of the repository name, which must not split the path.
+    fn url(&self, segments: &[&str]) -> Result<Url, Error> {
+        let mut url = Url::parse(&self.url)
+            .with_context(|| format!("Invalid Opsgenie URL '{}'", self.url))?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Invalid Opsgenie URL '{}'", self.url))?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<(), Error> {
+        let response = request.send().await?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Failed to send Opsgenie request. Status code: {}", response.status()))
+        }
+    }
+
+    async fn create_alert(&self, msg: &str, result: &ValidatorResult, secret: &GitHubAPISecret, repository: &GitHubAPIRepository) -> Result<(), Error> {
+        info!("Creating Opsgenie alert for {} in {}.", secret.name, repository.full_name);
+        // Opsgenie truncates messages beyond 130 characters.
+        let message: String = format!(
+            "Secret {} in {} is {} days overdue for rotation",
+            secret.name, repository.full_name, result.days_overdue
+        ).chars().take(130).collect();
+        self.send(self.post(self.url(&["v2", "alerts"])?).json(&json!({
+            "message": message,
+            "alias": alert_key(secret, repository),
+            "description": msg,
+            "source": SOURCE,
+            "entity": repository.full_name,
+            "priority": if result.severity == Severity::Critical { "P1" } else { "P2" },
+            "details": {
+                "repository": repository.full_name,
+                "secret": secret.name,
+                "days_age": result.days_age.to_string(),
+                "days_overdue": result.days_overdue.to_string(),
+                "exposure": format!("{:?}", result.exposure),
+                "owner": result.owner().unwrap_or("unknown"),
+            }
+        }))).await
+    }
+
+    async fn close_alert(&mut self, key: &str) -> Result<(), Error> {
+        info!("Closing Opsgenie alert {}.", key);
+        let request = self.post(self.url(&["v2", "alerts", key, "close"])?)
+            .query(&[("identifierType", "alias")])
+            .json(&json!({
+                "source": SOURCE,
+                "note": "Secret has been rotated.",
+            }));
+        let response = request.send().await?;
+        // A missing alert is reported as 404, e.g. if it was deleted in Opsgenie.
+        if response.status().is_success() || response.status() == 404 {
+            self.alerts.close(key);
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Failed to close Opsgenie alert. Status code: {}", response.status()))
+        }
+    }
+}
+
+#[async_trait]
+impl NotifiactionService for OpsgenieNotifier {
+    fn name(&self) -> &'static str {
+        "opsgenie"
+    }
+
+    async fn send_msg(&self, _: &str, _: &ValidatorState) -> Result<(), Error> {
+        // Alerts are raised per secret in `send_result`.
+        Ok(())
+    }
+
+    async fn send_result(
+        &mut self,
+        msg: &str,
+        result: &ValidatorResult,
+        secret: &GitHubAPISecret,
+        repository: &GitHubAPIRepository
+    ) -> Result<(), Error> {
+        let key = alert_key(secret, repository);
+        if requires_alert(result, self.alert_overdue_days) {
+            self.create_alert(msg, result, secret, repository).await?;
+            self.alerts.open(key, repository);
+        } else if self.alerts.is_open(&key) {
+            // Rotated or ignored since the alert was raised.
+            self.close_alert(&key).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_scanned(&mut self, repository: &GitHubAPIRepository, secrets: &[GitHubAPISecret]) -> Result<(), Error> {
+        for key in self.alerts.vanished(repository, secrets) {
+            self.close_alert(&key).await?;
+        }
+        Ok(())
+    }
+
+    fn open_alerts(&self) -> Option<&OpenAlerts> {
+        Some(&self.alerts)
+    }
+
+    fn restore_alerts(&mut self, alerts: OpenAlerts) {
+        self.alerts = alerts;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_close_url_escapes_alias() {
+        let notifier = OpsgenieNotifier::new("key".to_string(), Some("https://api.eu.opsgenie.com/".to_string()), 30);
+        let url = notifier.url(&["v2", "alerts", "ghso:my_org/my_repo:SECRET", "close"]).unwrap();
+        assert_eq!(url.as_str(), "https://api.eu.opsgenie.com/v2/alerts/ghso:my_org%2Fmy_repo:SECRET/close");
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::Error;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+use tracing::info;
+
+use crate::github_api::{GitHubAPIRepository, GitHubAPISecret};
+use crate::validator::{Severity, ValidatorResult, ValidatorState};
+use super::{alert_key, requires_alert, NotifiactionService, OpenAlerts};
+
+const EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+pub struct PagerDutyNotifier {
+    routing_key: String,
+    alert_overdue_days: i64,
+    client: Client,
+    alerts: OpenAlerts,
+}
+
+impl PagerDutyNotifier {
+    pub fn new(routing_key: String, alert_overdue_days: i64) -> Self {
+        PagerDutyNotifier {
+            routing_key,
+            alert_overdue_days,
+            client: Client::new(),
+            alerts: OpenAlerts::default(),
+        }
+    }
+
+    async fn send_event(&self, event: serde_json::Value) -> Result<(), Error> {
+        let response = self.client.post(EVENTS_URL)
+            .json(&event)
+            .send().await?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Failed to send PagerDuty event. Status code: {}", response.status()))
+        }
+    }
+
+    async fn trigger(&self, msg: &str, result: &ValidatorResult, secret: &GitHubAPISecret, repository: &GitHubAPIRepository) -> Result<(), Error> {
+        info!("Triggering PagerDuty alert for {} in {}.", secret.name, repository.full_name);
+        self.send_event(json!({
+            "routing_key": self.routing_key,
+            "event_action": "trigger",
+            "dedup_key": alert_key(secret, repository),
+            "payload": {
+                "summary": format!(
+                    "Secret {} in {} is {} days overdue for rotation",
+                    secret.name, repository.full_name, result.days_overdue
+                ),
+                "source": repository.full_name,
+                "severity": if result.severity == Severity::Critical { "critical" } else { "error" },
+                "component": secret.name,
+                "custom_details": {
+                    "message": msg,
+                    "days_age": result.days_age,
+                    "days_overdue": result.days_overdue,
+                    "exposure": result.exposure,
+                    "updated_at": secret.updated_at,
+                    "owner": result.owner(),
+                }
+            }
+        })).await
+    }
+
+    async fn resolve(&mut self, key: &str) -> Result<(), Error> {
+        info!("Resolving PagerDuty alert {}.", key);
+        self.send_event(json!({
+            "routing_key": self.routing_key,
+            "event_action": "resolve",
+            "dedup_key": key,
+        })).await?;
+        self.alerts.close(key);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl NotifiactionService for PagerDutyNotifier {
+    fn name(&self) -> &'static str {
+        "pagerduty"
+    }
+
+    async fn send_msg(&self, _: &str, _: &ValidatorState) -> Result<(), Error> {
+        // Alerts are raised per secret in `send_result`.
+        Ok(())
+    }
+
+    async fn send_result(
+        &mut self,
+        msg: &str,
+        result: &ValidatorResult,
+        secret: &GitHubAPISecret,
+        repository: &GitHubAPIRepository
+    ) -> Result<(), Error> {
+        let key = alert_key(secret, repository);
+        if requires_alert(result, self.alert_overdue_days) {
+            self.trigger(msg, result, secret, repository).await?;
+            self.alerts.open(key, repository);
+        } else if self.alerts.is_open(&key) {
+            // Rotated or ignored since the alert was raised.
+            self.resolve(&key).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_scanned(&mut self, repository: &GitHubAPIRepository, secrets: &[GitHubAPISecret]) -> Result<(), Error> {
+        for key in self.alerts.vanished(repository, secrets) {
+            self.resolve(&key).await?;
+        }
+        Ok(())
+    }
+
+    fn open_alerts(&self) -> Option<&OpenAlerts> {
+        Some(&self.alerts)
+    }
+
+    fn restore_alerts(&mut self, alerts: OpenAlerts) {
+        self.alerts = alerts;
+    }
+}
@@ -0,0 +1,409 @@
+use std::fmt;
+
+use anyhow::Error;
+use chrono::prelude::{DateTime, Utc};
+use handlebars::{Handlebars, TemplateError};
+use serde::Serialize;
+
+use crate::audit::Finding;
+use crate::changes::ChangeReport;
+use crate::config::TemplateConfig;
+use crate::github_api::{GitHubAPIRepository, GitHubAPISecret};
+use crate::observer::RepositoryFailure;
+use crate::validator::{Severity, ValidatorResult, ValidatorState};
+use crate::workflows::{Exposure, UsageReport};
+
+const MESSAGE: &str = "message";
+const DIGEST: &str = "digest";
+const CHANGES: &str = "changes";
+const FAILURES: &str = "failures";
+const USAGE: &str = "usage";
+const FINDINGS: &str = "findings";
+
+/// Reproduces the message format used before templates were configurable.
+pub const DEFAULT_MESSAGE_TEMPLATE: &str = "{{icon}} [state={{state}}, name={{name}}, repository={{repository}}, \
+    days_age={{days_age}}, days_left={{days_left}}, days_overdue={{days_overdue}}] {{description}}";
+
+pub const DEFAULT_CHANGES_TEMPLATE: &str = "🔄 {{len rotated}} secrets rotated, {{len created}} new secrets, \
+    {{len deleted}} secrets deleted, {{len newly_expired}} secrets newly expired.";
+
+pub const DEFAULT_FAILURES_TEMPLATE: &str = "⚠️ {{len repositories}} repositories not scanned:\
+    {{#each repositories}}\n- {{repository}}: {{reason}}{{/each}}";
+
+pub const DEFAULT_USAGE_TEMPLATE: &str = "🔍 {{len unused}} unused secrets, {{len missing}} missing secrets:\
+    {{#each unused}}\n- unused: {{repository}} {{secret}}{{/each}}\
+    {{#each missing}}\n- missing: {{repository}} {{secret}} in {{#each workflows}}{{#unless @first}}, {{/unless}}{{this}}{{/each}}{{/each}}";
+
+pub const DEFAULT_FINDINGS_TEMPLATE: &str = "🛡️ {{len findings}} audit findings:\
+    {{#each findings}}\n- [{{severity}}] {{target}} {{subject}}: {{message}}{{/each}}";
+
+/// Fields available in the findings template.
+#[derive(Debug, Serialize)]
+pub struct FindingsContext<'a> {
+    pub findings: &'a [Finding],
+}
+
+/// Fields available in the failures template.
+#[derive(Debug, Serialize)]
+pub struct FailuresContext<'a> {
+    pub repositories: &'a [RepositoryFailure],
+}
+
+/// Fields available in the per secret message template.
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageContext {
+    pub state: ValidatorState,
+    pub name: String,
+    pub repository: String,
+    pub days_age: i64,
+    pub days_left: i64,
+    pub days_overdue: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub icon: &'static str,
+    pub description: &'static str,
+    /// Workflows that reference the secret, empty unless workflows are checked.
+    pub workflows: Vec<String>,
+    pub exposure: Exposure,
+    pub severity: Severity,
+    /// Who last set the secret and when. Flat and empty unless updates are attributed,
+    /// as strict mode rejects nested fields of a missing value.
+    pub updated_by_actor: String,
+    pub updated_by_timestamp: String,
+    /// Person to notify about the secret, the actor of the last update.
+    pub owner: Option<String>,
+}
+
+impl MessageContext {
+    pub fn new(result: &ValidatorResult, secret: &GitHubAPISecret, repository: &GitHubAPIRepository) -> Self {
+        let (icon, description) = match result.state {
+            ValidatorState::Expired => ("❌", "Secret is expired."),
+            ValidatorState::NotExpired => ("✅", "Secret is not expired."),
+            ValidatorState::Ignored => ("🤷", "Secret is ignored."),
+            ValidatorState::ExpiresSoon => ("⚠️", "Secret expires soon."),
+        };
+        MessageContext {
+            state: result.state,
+            name: secret.name.clone(),
+            repository: repository.full_name.clone(),
+            days_age: result.days_age,
+            days_left: result.days_left,
+            days_overdue: result.days_overdue,
+            created_at: secret.created_at,
+            updated_at: secret.updated_at,
+            icon,
+            description,
+            workflows: secret.workflows.clone(),
+            exposure: result.exposure,
+            severity: result.severity,
+            updated_by_actor: result.updated_by.as_ref().map(|update| update.actor.clone()).unwrap_or_default(),
+            updated_by_timestamp: result.updated_by.as_ref().map(|update| update.timestamp.to_rfc3339()).unwrap_or_default(),
+            owner: result.owner().map(str::to_string),
+        }
+    }
+}
+
+/// Fields available in the digest template, which is rendered once per run.
+#[derive(Debug, Serialize)]
+pub struct DigestContext<'a> {
+    pub results: &'a [MessageContext],
+    /// Repositories that could not be scanned.
+    pub skipped: &'a [RepositoryFailure],
+    pub total: usize,
+    pub expired: usize,
+    pub expires_soon: usize,
+    pub not_expired: usize,
+    pub ignored: usize,
+}
+
+impl<'a> DigestContext<'a> {
+    pub fn new(results: &'a [MessageContext], skipped: &'a [RepositoryFailure]) -> Self {
+        let count = |state: ValidatorState| results.iter().filter(|r| r.state == state).count();
+        DigestContext {
+            results,
+            skipped,
+            total: results.len(),
+            expired: count(ValidatorState::Expired),
+            expires_soon: count(ValidatorState::ExpiresSoon),
+            not_expired: count(ValidatorState::NotExpired),
+            ignored: count(ValidatorState::Ignored),
+        }
+    }
+
+    /// The most severe state in the digest, used to pick the log level or annotation.
+    pub fn state(&self) -> ValidatorState {
+        if self.expired > 0 {
+            ValidatorState::Expired
+        } else if self.expires_soon > 0 {
+            ValidatorState::ExpiresSoon
+        } else {
+            ValidatorState::NotExpired
+        }
+    }
+}
+
+/// A configured template that does not compile.
+#[derive(Debug)]
+pub struct InvalidTemplate {
+    /// Key of the template in `notifier.templates.<name>`, e.g. `message`.
+    pub key: &'static str,
+    source: TemplateError,
+}
+
+impl InvalidTemplate {
+    fn new(key: &'static str, source: TemplateError) -> Self {
+        InvalidTemplate { key, source }
+    }
+}
+
+impl fmt::Display for InvalidTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid {} template", self.key)
+    }
+}
+
+impl std::error::Error for InvalidTemplate {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Compiled message and digest templates of a single notifier.
+pub struct MessageTemplates {
+    registry: Handlebars<'static>,
+    has_digest: bool,
+}
+
+impl MessageTemplates {
+    pub fn new(config: Option<&TemplateConfig>) -> Result<Self, Error> {
+        let mut registry = Handlebars::new();
+        // Messages are plain text, so values must not be HTML escaped.
+        registry.register_escape_fn(handlebars::no_escape);
+        registry.set_strict_mode(true);
+
+        let message = config
+            .and_then(|c| c.message.as_deref())
+            .unwrap_or(DEFAULT_MESSAGE_TEMPLATE);
+        registry.register_template_string(MESSAGE, message)
+            .map_err(|e| InvalidTemplate::new(MESSAGE, e))?;
+
+        let digest = config.and_then(|c| c.digest.as_deref());
+        if let Some(digest) = digest {
+            registry.register_template_string(DIGEST, digest)
+                .map_err(|e| InvalidTemplate::new(DIGEST, e))?;
+        }
+
+        let changes = config
+            .and_then(|c| c.changes.as_deref())
+            .unwrap_or(DEFAULT_CHANGES_TEMPLATE);
+        registry.register_template_string(CHANGES, changes)
+            .map_err(|e| InvalidTemplate::new(CHANGES, e))?;
+
+        let failures = config
+            .and_then(|c| c.failures.as_deref())
+            .unwrap_or(DEFAULT_FAILURES_TEMPLATE);
+        registry.register_template_string(FAILURES, failures)
+            .map_err(|e| InvalidTemplate::new(FAILURES, e))?;
+
+        let usage = config
+            .and_then(|c| c.usage.as_deref())
+            .unwrap_or(DEFAULT_USAGE_TEMPLATE);
+        registry.register_template_string(USAGE, usage)
+            .map_err(|e| InvalidTemplate::new(USAGE, e))?;
+
+        let findings = config
+            .and_then(|c| c.findings.as_deref())
+            .unwrap_or(DEFAULT_FINDINGS_TEMPLATE);
+        registry.register_template_string(FINDINGS, findings)
+            .map_err(|e| InvalidTemplate::new(FINDINGS, e))?;
+
+        Ok(MessageTemplates {
+            registry,
+            has_digest: digest.is_some(),
+        })
+    }
+
+    pub fn has_digest(&self) -> bool {
+        self.has_digest
+    }
+
+    pub fn render_message(&self, context: &MessageContext) -> Result<String, Error> {
+        Ok(self.registry.render(MESSAGE, context)?)
+    }
+
+    pub fn render_digest(&self, context: &DigestContext) -> Result<Option<String>, Error> {
+        if !self.has_digest {
+            return Ok(None);
+        }
+        Ok(Some(self.registry.render(DIGEST, context)?))
+    }
+
+    pub fn render_changes(&self, report: &ChangeReport) -> Result<String, Error> {
+        Ok(self.registry.render(CHANGES, report)?)
+    }
+
+    pub fn render_failures(&self, failures: &[RepositoryFailure]) -> Result<String, Error> {
+        Ok(self.registry.render(FAILURES, &FailuresContext { repositories: failures })?)
+    }
+
+    pub fn render_usage(&self, report: &UsageReport) -> Result<String, Error> {
+        Ok(self.registry.render(USAGE, report)?)
+    }
+
+    pub fn render_findings(&self, findings: &[Finding]) -> Result<String, Error> {
+        Ok(self.registry.render(FINDINGS, &FindingsContext { findings })?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::FindingKind;
+    use crate::workflows::SecretUsage;
+
+    fn context(state: ValidatorState) -> MessageContext {
+        let secret = GitHubAPISecret {
+            name: "SECRET".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            ..Default::default()
+        };
+        let repository: GitHubAPIRepository = serde_json::from_value(serde_json::json!({
+            "id": 1, "name": "my_repo", "full_name": "my_org/my_repo"
+        })).unwrap();
+        let result = ValidatorResult {
+            state,
+            days_age: 170,
+            days_left: 0,
+            days_overdue: 80,
+            exposure: Exposure::Trusted,
+            severity: Severity::High,
+            updated_by: None,
+        };
+        MessageContext::new(&result, &secret, &repository)
+    }
+
+    #[test]
+    fn test_default_template_is_unchanged() {
+        let templates = MessageTemplates::new(None).unwrap();
+        assert_eq!(
+            templates.render_message(&context(ValidatorState::Expired)).unwrap(),
+            "❌ [state=Expired, name=SECRET, repository=my_org/my_repo, days_age=170, days_left=0, days_overdue=80] Secret is expired."
+        );
+        assert!(!templates.has_digest());
+    }
+
+    #[test]
+    fn test_updated_by_without_attribution() {
+        let config = TemplateConfig {
+            message: Some("{{name}} by {{updated_by_actor}} at {{updated_by_timestamp}}{{#if owner}} cc @{{owner}}{{/if}}".to_string()),
+            digest: None,
+            changes: None,
+            failures: None,
+            usage: None,
+            findings: None,
+        };
+        let templates = MessageTemplates::new(Some(&config)).unwrap();
+        assert_eq!(templates.render_message(&context(ValidatorState::Expired)).unwrap(), "SECRET by  at ");
+
+        let mut context = context(ValidatorState::Expired);
+        context.updated_by_actor = "octocat".to_string();
+        context.owner = Some("octocat".to_string());
+        assert_eq!(templates.render_message(&context).unwrap().split(" at ").next(), Some("SECRET by octocat"));
+        assert!(templates.render_message(&context).unwrap().ends_with(" cc @octocat"));
+    }
+
+    #[test]
+    fn test_custom_templates() {
+        let config = TemplateConfig {
+            message: Some("{{name}} <{{state}}>".to_string()),
+            digest: Some("{{expired}}/{{total}}{{#each results}} {{name}}{{/each}}".to_string()),
+            changes: None,
+            failures: None,
+            usage: None,
+            findings: None,
+        };
+        let templates = MessageTemplates::new(Some(&config)).unwrap();
+        let results = vec![context(ValidatorState::Expired), context(ValidatorState::NotExpired)];
+        assert_eq!(templates.render_message(&results[0]).unwrap(), "SECRET <Expired>");
+        assert_eq!(
+            templates.render_digest(&DigestContext::new(&results, &[])).unwrap(),
+            Some("1/2 SECRET SECRET".to_string())
+        );
+    }
+
+    #[test]
+    fn test_default_changes_template() {
+        let templates = MessageTemplates::new(None).unwrap();
+        assert_eq!(
+            templates.render_changes(&ChangeReport::default()).unwrap(),
+            "🔄 0 secrets rotated, 0 new secrets, 0 secrets deleted, 0 secrets newly expired."
+        );
+    }
+
+    #[test]
+    fn test_default_failures_template() {
+        let templates = MessageTemplates::new(None).unwrap();
+        let failures = vec![RepositoryFailure {
+            repository: "my_org/my_repo".to_string(),
+            reason: "Resource not accessible by integration".to_string(),
+        }];
+        assert_eq!(
+            templates.render_failures(&failures).unwrap(),
+            "⚠️ 1 repositories not scanned:\n- my_org/my_repo: Resource not accessible by integration"
+        );
+    }
+
+    #[test]
+    fn test_unknown_field_fails() {
+        let config = TemplateConfig {
+            message: Some("{{unknown}}".to_string()),
+            digest: None,
+            changes: None,
+            failures: None,
+            usage: None,
+            findings: None,
+        };
+        let templates = MessageTemplates::new(Some(&config)).unwrap();
+        assert!(templates.render_message(&context(ValidatorState::Expired)).is_err());
+    }
+
+    #[test]
+    fn test_default_usage_template() {
+        let templates = MessageTemplates::new(None).unwrap();
+        let report = UsageReport {
+            unused: vec![SecretUsage {
+                repository: "my_org/my_repo".to_string(),
+                secret: "OLD_TOKEN".to_string(),
+                workflows: Vec::new(),
+            }],
+            missing: vec![SecretUsage {
+                repository: "my_org/my_repo".to_string(),
+                secret: "NPM_TOKEN".to_string(),
+                workflows: vec![".github/workflows/ci.yml".to_string(), ".github/workflows/release.yml".to_string()],
+            }],
+        };
+        assert_eq!(
+            templates.render_usage(&report).unwrap(),
+            "🔍 1 unused secrets, 1 missing secrets:\n- unused: my_org/my_repo OLD_TOKEN\n\
+            - missing: my_org/my_repo NPM_TOKEN in .github/workflows/ci.yml, .github/workflows/release.yml"
+        );
+    }
+
+    #[test]
+    fn test_default_findings_template() {
+        let templates = MessageTemplates::new(None).unwrap();
+        let findings = vec![Finding {
+            kind: FindingKind::UnprotectedEnvironment,
+            severity: Severity::High,
+            target: "my_org/my_repo".to_string(),
+            subject: "production".to_string(),
+            message: "Environment is not protected.".to_string(),
+            owners: Vec::new(),
+        }];
+        assert_eq!(
+            templates.render_findings(&findings).unwrap(),
+            "🛡️ 1 audit findings:\n- [High] my_org/my_repo production: Environment is not protected."
+        );
+    }
+}
@@ -1,48 +1,401 @@
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
 use anyhow::Error;
-use tracing::error;
+use serde::Serialize;
+use tokio::sync::watch;
+use tracing::{error, warn};
 
-use crate::github_api::GitHubAPI;
-use crate::config::Configuration;
-use crate::validator::Validator;
+use crate::attribution::{SecretScope, SecretUpdates};
+use crate::audit::{
+    Finding, audit_environments, audit_organization_secrets, audit_organization_variables,
+    audit_organization_webhooks, audit_variables, audit_webhooks, credential_authorizations, credential_findings, personal_access_tokens, validate_credentials, validate_deploy_keys
+};
+use crate::changes::ChangeReport;
+use crate::github_api::{GitHubAPI, GitHubAPIEnvironment, GitHubAPISecret};
+use crate::history::History;
+use crate::metrics::{Metrics, SharedMetrics};
+use crate::config::{Configuration, InvalidConfiguration};
+use crate::validator::{Severity, Validator, ValidatorState};
 use crate::notifier::Notifier;
+use crate::workflows::{fetch_workflow_files, SecretReferences, SecretUsage, UsageReport};
+
 
+/// A repository that could not be scanned, e.g. due to missing permissions.
+#[derive(Debug, Clone, Serialize)]
+pub struct RepositoryFailure {
+    pub repository: String,
+    pub reason: String,
+}
+
+/// Findings of a run that require action.
+#[derive(Debug, Default, Clone)]
+pub struct ScanSummary {
+    pub expired: usize,
+    pub expires_soon: usize,
+    /// Audit findings with a severity of at least `High`.
+    pub severe_findings: usize,
+    pub failed_repositories: Vec<RepositoryFailure>,
+}
 
 pub struct Observer {
     config: Configuration,
     validator: Validator,
     notifier: Notifier,
     github_api: GitHubAPI,
+    history: Option<History>,
+    shutdown: Option<watch::Receiver<bool>>,
+    metrics: SharedMetrics,
 }
 
 impl Observer {
     pub async fn new(config: Configuration) -> Result<Observer, Error> {
         let validator = Validator::new(config.observer.clone());
-        let notifier = Notifier::new(config.notifier.clone()).await?;
-        let github_api = GitHubAPI::new(None, Some(config.github.token.clone()));
+        let github_api = GitHubAPI::new(None, config.github.api_token())?;
+        let notifier = Notifier::new(config.notifier.clone(), github_api.clone()).await?;
+        let history = config.history.file.as_ref().map(|file| History::new(file.into()));
         Ok(Observer {
             config,
             validator,
             notifier,
-            github_api
+            github_api,
+            history,
+            shutdown: None,
+            metrics: Arc::new(RwLock::new(Metrics::new())),
         })
     }
 
-    pub async fn run(&mut self) -> Result<(), Error> {
-        if self.config.github.organization.is_none() {
-            error!("No organization provided. Please provide an organization via CLI, environment or config file.");
-            return Err(anyhow::anyhow!("No organization provided."));
+    /// Metrics of the last finished scan.
+    pub fn metrics(&self) -> SharedMetrics {
+        self.metrics.clone()
+    }
+
+    /// Lets a running scan stop after the current repository once `true` is sent.
+    pub fn set_shutdown_signal(&mut self, shutdown: watch::Receiver<bool>) {
+        self.shutdown = Some(shutdown);
+    }
+
+    fn shutdown_requested(&self) -> bool {
+        self.shutdown.as_ref().is_some_and(|shutdown| *shutdown.borrow())
+    }
+
+    pub async fn run(&mut self) -> Result<ScanSummary, Error> {
+        if self.config.github.organization.is_none() && self.config.github.repositories().is_none() {
+            error!("No organization provided. Please provide an organization or repositories via CLI, environment or config file.");
+            return Err(InvalidConfiguration("No organization provided.".to_string()).into());
+        }
+
+        let mut summary = ScanSummary::default();
+        // The observer is reused by the daemon, a failed run must not leak into this one.
+        self.notifier.reset();
+        if let Some(history) = self.history.as_mut() {
+            history.start_run();
         }
+        let started = Instant::now();
+        let mut metrics = Metrics::new();
+        let mut usage = UsageReport::default();
+        let mut findings = Vec::new();
 
-        let repositories = self.github_api.get_repositories(
-            self.config.github.organization.clone().unwrap().as_str()
-        ).await?;
+        let repositories = match self.config.github.repositories() {
+            Some(names) => {
+                let mut repositories = Vec::new();
+                for name in names {
+                    match self.github_api.get_repository(name).await {
+                        Ok(repository) => repositories.push(repository),
+                        Err(e) => self.record_failure(&mut summary, name, e)?,
+                    }
+                }
+                repositories
+            },
+            None => self.github_api.get_repositories(
+                self.config.github.organization.clone().unwrap().as_str()
+            ).await?,
+        };
+        let updates = self.secret_updates().await;
         for repository in repositories {
-            let github_secrets = self.github_api.get_secrets(&repository).await?;
-            for secret in  github_secrets.secrets.iter() {
-                let validator_result = self.validator.validate_secret(secret).await?;
+            if self.shutdown_requested() {
+                // Nothing is flushed or saved, so a partial scan does not end up in
+                // the history or close issues of repositories that were not scanned.
+                warn!("Shutdown requested, stopping scan before {}.", repository.full_name);
+                return Ok(summary);
+            }
+            let mut github_secrets = match self.github_api.get_secrets(&repository).await {
+                Ok(github_secrets) => github_secrets,
+                Err(e) => {
+                    self.record_failure(&mut summary, &repository.full_name, e)?;
+                    continue;
+                }
+            };
+            self.notifier.report_scanned(&repository, &github_secrets.secrets).await?;
+            if let Some(history) = self.history.as_mut() {
+                history.scanned(&repository);
+            }
+            let environments = self.environments(&repository.full_name).await;
+            if let (true, Some(environments)) = (self.config.observer.check_workflows, environments.as_deref()) {
+                match self.check_workflows(&repository.full_name, &mut github_secrets.secrets, environments).await {
+                    Ok(report) => {
+                        usage.unused.extend(report.unused);
+                        usage.missing.extend(report.missing);
+                    },
+                    // The secrets are still validated, just without their workflows.
+                    Err(e) => warn!("Failed to check the workflows of {}. Reason: {:#}", repository.full_name, e),
+                }
+            }
+            findings.extend(self.audit_repository(&repository.full_name, environments.as_deref(), &updates).await);
+            if self.config.audit.deploy_keys {
+                match validate_deploy_keys(&self.github_api, &self.validator, &repository.full_name).await {
+                    Ok(keys) => {
+                        for key in keys {
+                            match key.result.state {
+                                ValidatorState::Expired => summary.expired += 1,
+                                ValidatorState::ExpiresSoon => summary.expires_soon += 1,
+                                _ => {},
+                            }
+                            metrics.record_secret(
+                                &repository.full_name, &key.key.title, "deploy_key",
+                                &key.result.state, key.result.days_age
+                            );
+                            findings.extend(key.finding(&repository.full_name));
+                        }
+                    },
+                    Err(e) => warn!("Failed to validate the deploy keys of {}. Reason: {:#}", repository.full_name, e),
+                }
+            }
+            for secret in github_secrets.secrets.iter() {
+                let mut validator_result = self.validator.validate_secret(secret).await?;
+                validator_result.updated_by = updates.attribute(
+                    SecretScope::Repository(repository.full_name.clone()), &secret.name, secret.updated_at
+                );
+                match validator_result.state {
+                    ValidatorState::Expired => summary.expired += 1,
+                    ValidatorState::ExpiresSoon => summary.expires_soon += 1,
+                    _ => {},
+                }
                 self.notifier.notify(&validator_result, secret, &repository).await?;
+                if let Some(history) = self.history.as_mut() {
+                    history.record(&validator_result, secret, &repository);
+                }
+                metrics.record_secret(
+                    &repository.full_name, &secret.name, "repository",
+                    &validator_result.state, validator_result.days_age
+                );
+            }
+        }
+        let audit = &self.config.audit;
+        if audit.organization_secrets || audit.variables || audit.webhooks
+            || audit.personal_access_tokens || audit.credential_authorizations {
+            match self.config.github.organization.as_deref() {
+                Some(organization) => findings.extend(self.audit_organization(organization, &updates, &mut summary, &mut metrics).await),
+                None => warn!("No organization provided, skipping the organization audits."),
+            }
+        }
+        summary.severe_findings = findings.iter().filter(|finding| finding.severity >= Severity::High).count();
+        if !usage.is_empty() {
+            self.notifier.report_usage(&usage).await?;
+        }
+        if !findings.is_empty() {
+            self.notifier.report_findings(&findings).await?;
+        }
+        if !summary.failed_repositories.is_empty() {
+            self.notifier.report_failures(&summary.failed_repositories).await?;
+        }
+        if let Some(history) = self.history.as_mut() {
+            // The first run has no baseline, so every secret would be reported as new.
+            let previous = history.last_snapshot()?;
+            if !previous.is_empty() {
+                let report = ChangeReport::new(&previous, &history.pending());
+                metrics.record_changes(&report);
+                if !report.is_empty() {
+                    self.notifier.report_changes(&report).await?;
+                }
+            }
+        }
+        self.notifier.flush().await?;
+        if let Some(history) = self.history.as_mut() {
+            history.save()?;
+        }
+
+        metrics.finish_scan(
+            started.elapsed().as_secs_f64(),
+            chrono::Utc::now().timestamp(),
+            self.github_api.request_count(),
+            self.github_api.rate_limit_remaining()
+        );
+        if let Some(textfile) = &self.config.metrics.textfile {
+            metrics.write_textfile(std::path::Path::new(textfile))?;
+        }
+        if let Ok(mut shared) = self.metrics.write() {
+            *shared = metrics;
+        }
+        Ok(summary)
+    }
+
+    /// Who last set the secrets, if enabled. The scan continues without attribution if
+    /// the audit log can't be read, e.g. outside of GitHub Enterprise Cloud.
+    async fn secret_updates(&self) -> SecretUpdates {
+        if !self.config.observer.attribute_updates {
+            return SecretUpdates::default();
+        }
+        let Some(organization) = self.config.github.organization.as_deref() else {
+            warn!("No organization provided, secret updates are not attributed.");
+            return SecretUpdates::default();
+        };
+        match SecretUpdates::fetch(&self.github_api, organization).await {
+            Ok(updates) => updates,
+            Err(e) => {
+                warn!("Failed to read the audit log of {}, secret updates are not attributed. Reason: {:#}", organization, e);
+                SecretUpdates::default()
+            }
+        }
+    }
+
+    /// Environments of a repository, read once for the workflow check and the audits
+    /// that need them. `None` if they are not needed or could not be read, in which
+    /// case the environment audits and the workflow check are skipped.
+    async fn environments(&self, repository: &str) -> Option<Vec<GitHubAPIEnvironment>> {
+        if !self.config.observer.check_workflows && !self.config.audit.environments && !self.config.audit.variables {
+            return None;
+        }
+        match self.github_api.get_environments(repository).await {
+            Ok(environments) => Some(environments),
+            Err(e) => {
+                warn!("Failed to read the environments of {}, skipping the environment audits and the workflow check. Reason: {:#}", repository, e);
+                None
+            }
+        }
+    }
+
+    /// Runs the enabled audits of a single repository. A failed audit is logged and
+    /// neither stops the other audits nor the validation of the secrets.
+    async fn audit_repository(&self, repository: &str, environments: Option<&[GitHubAPIEnvironment]>, updates: &SecretUpdates) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        if let (true, Some(environments)) = (self.config.audit.environments, environments) {
+            findings.extend(audit_findings(repository, "environments", audit_environments(&self.github_api, repository, environments, updates).await));
+        }
+        if self.config.audit.variables {
+            let environments = environments.unwrap_or_default();
+            findings.extend(audit_findings(repository, "variables", audit_variables(&self.github_api, repository, environments).await));
+        }
+        if self.config.audit.webhooks {
+            findings.extend(audit_findings(repository, "webhooks", audit_webhooks(&self.github_api, &self.validator, repository).await));
+        }
+        findings
+    }
+
+    /// Runs the enabled audits of the organization. A failed audit is logged like
+    /// those of repositories and does not count towards `max_failed_repositories`,
+    /// the other audits still run.
+    async fn audit_organization(
+        &self,
+        organization: &str,
+        updates: &SecretUpdates,
+        summary: &mut ScanSummary,
+        metrics: &mut Metrics
+    ) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        if self.config.audit.organization_secrets {
+            let result = audit_organization_secrets(&self.github_api, organization, updates).await;
+            findings.extend(audit_findings(organization, "secrets", result));
+        }
+        if self.config.audit.variables {
+            let result = audit_organization_variables(&self.github_api, organization).await;
+            findings.extend(audit_findings(organization, "variables", result));
+        }
+        if self.config.audit.webhooks {
+            let result = audit_organization_webhooks(&self.github_api, &self.validator, organization).await;
+            findings.extend(audit_findings(organization, "webhooks", result));
+        }
+        let mut credentials = Vec::new();
+        if self.config.audit.personal_access_tokens {
+            let result = personal_access_tokens(&self.github_api, organization).await;
+            credentials.extend(audit_findings(organization, "personal access tokens", result));
+        }
+        if self.config.audit.credential_authorizations {
+            let result = credential_authorizations(&self.github_api, organization).await;
+            credentials.extend(audit_findings(organization, "credential authorizations", result));
+        }
+        let now = chrono::Utc::now();
+        let results = validate_credentials(&self.validator, &credentials, now).await;
+        for credential in audit_findings(organization, "credentials", results) {
+            match credential.result.state {
+                ValidatorState::Expired => summary.expired += 1,
+                ValidatorState::ExpiresSoon => summary.expires_soon += 1,
+                _ => {},
+            }
+            metrics.record_secret(
+                organization, &credential.credential.subject(), "credential",
+                &credential.result.state, credential.result.days_age
+            );
+            findings.extend(credential.finding(organization));
+        }
+        findings.extend(credential_findings(
+            organization, &credentials, self.config.audit.unused_credential_days, now
+        ));
+        findings
+    }
+
+    /// Attaches the referencing workflows to the secrets and reports secrets that are
+    /// never referenced, and references to secrets that don't exist at repository,
+    /// environment or organization level.
+    async fn check_workflows(&self, repository: &str, secrets: &mut [GitHubAPISecret], environments: &[GitHubAPIEnvironment]) -> Result<UsageReport, Error> {
+        let files = fetch_workflow_files(&self.github_api, repository).await?;
+        let references = SecretReferences::parse(&files);
+
+        let mut available: HashSet<String> = secrets.iter().map(|secret| secret.name.to_uppercase()).collect();
+        for environment in environments {
+            let environment_secrets = self.github_api.get_environment_secrets(repository, &environment.name).await?;
+            available.extend(environment_secrets.secrets.iter().map(|secret| secret.name.to_uppercase()));
+        }
+        let organization_secrets = self.github_api.get_organization_secrets(repository).await?;
+        available.extend(organization_secrets.secrets.iter().map(|secret| secret.name.to_uppercase()));
+
+        let mut report = UsageReport::default();
+        for secret in secrets.iter_mut() {
+            secret.workflows = references.workflows(&secret.name);
+            secret.exposure = references.exposure(&secret.name);
+            if !references.is_used(&secret.name) {
+                report.unused.push(SecretUsage {
+                    repository: repository.to_string(),
+                    secret: secret.name.clone(),
+                    workflows: Vec::new(),
+                });
+            }
+        }
+        for (name, workflows) in references.missing(&available) {
+            report.missing.push(SecretUsage {
+                repository: repository.to_string(),
+                secret: name.clone(),
+                workflows: workflows.iter().cloned().collect(),
+            });
+        }
+        Ok(report)
+    }
+
+    /// Records a repository that could not be scanned. Fails once more than
+    /// `max_failed_repositories` repositories failed.
+    fn record_failure(&self, summary: &mut ScanSummary, repository: &str, e: Error) -> Result<(), Error> {
+        warn!("Failed to scan {}. Reason: {:#}", repository, e);
+        summary.failed_repositories.push(RepositoryFailure {
+            repository: repository.to_string(),
+            reason: format!("{:#}", e),
+        });
+        if let Some(max) = self.config.observer.max_failed_repositories {
+            if summary.failed_repositories.len() > max {
+                return Err(anyhow::anyhow!(
+                    "{} repositories could not be scanned, which exceeds max_failed_repositories ({}). Last error: {:#}",
+                    summary.failed_repositories.len(), max, e
+                ));
             }
         }
         Ok(())
     }
 }
+
+/// Findings of an audit, or none if it failed.
+fn audit_findings<T>(target: &str, audit: &str, result: Result<Vec<T>, Error>) -> Vec<T> {
+    result.unwrap_or_else(|e| {
+        warn!("Failed to audit the {} of {}. Reason: {:#}", audit, target, e);
+        Vec::new()
+    })
+}
@@ -1,13 +1,17 @@
-use anyhow::Error;
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::attribution::SecretUpdate;
 use crate::config::ObserverConfig;
 use crate::github_api::GitHubAPISecret;
+use crate::workflows::Exposure;
 
 pub struct Validator {
     config: ObserverConfig
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum ValidatorState {
     Expired,
     NotExpired,
@@ -15,11 +19,80 @@ pub enum ValidatorState {
     Ignored,
 }
 
+/// Urgency of a result, raised for stale secrets that untrusted code can reach.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn new(state: ValidatorState, exposure: Exposure) -> Self {
+        let severity = match state {
+            ValidatorState::Expired => Severity::High,
+            ValidatorState::ExpiresSoon => Severity::Medium,
+            ValidatorState::NotExpired | ValidatorState::Ignored => Severity::Low,
+        };
+        match (severity, exposure) {
+            (Severity::Low, _) | (_, Exposure::Trusted) => severity,
+            (Severity::Medium, _) => Severity::High,
+            _ => Severity::Critical,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ValidatorResult {
     pub state: ValidatorState,
     pub days_age: i64,
     pub days_left: i64,
     pub days_overdue: i64,
+    /// Exposure of the secret to untrusted workflow triggers.
+    pub exposure: Exposure,
+    pub severity: Severity,
+    /// Who set the current value. Only set when updates are attributed.
+    pub updated_by: Option<SecretUpdate>,
+}
+
+impl ValidatorResult {
+    /// The person responsible for the secret, by default whoever set its current value.
+    pub fn owner(&self) -> Option<&str> {
+        self.updated_by.as_ref().map(|update| update.actor.as_str())
+    }
+}
+
+/// Rotation period that applies to a secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RotationPolicy {
+    /// `default_rotation_days` of the configuration.
+    Default(i64),
+    /// Set by the `_R<days>` suffix of the secret name.
+    Suffix(i64),
+}
+
+impl RotationPolicy {
+    pub fn days(&self) -> i64 {
+        match self {
+            RotationPolicy::Default(days) | RotationPolicy::Suffix(days) => *days,
+        }
+    }
+}
+
+/// Reason why a secret is ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Exemption {
+    IgnoreList,
+    IgnorePattern(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidatorExplanation {
+    pub result: ValidatorResult,
+    pub policy: RotationPolicy,
+    pub exemption: Option<Exemption>,
+    pub notice_days: i64,
 }
 
 impl Validator {
@@ -30,6 +103,11 @@ impl Validator {
     }
 
     pub async fn validate_secret(&self, secret: &GitHubAPISecret) -> Result<ValidatorResult, Error> {
+        Ok(self.explain_secret(secret).await?.result)
+    }
+
+    /// Validates the secret and returns the policy and exemption that lead to the result.
+    pub async fn explain_secret(&self, secret: &GitHubAPISecret) -> Result<ValidatorExplanation, Error> {
         // The validator component will be responsible for validating the secrets to check if they are expired or not
         
         let mut result = ValidatorResult {
@@ -37,6 +115,9 @@ impl Validator {
             days_age: i64::default(),
             days_left: i64::default(),
             days_overdue: i64::default(),
+            exposure: secret.exposure,
+            severity: Severity::Low,
+            updated_by: None,
         };
 
         // Calculate the difference between the current date and the last update of the secret
@@ -44,37 +125,71 @@ impl Validator {
         let diff = now.signed_duration_since(secret.updated_at);
         result.days_age = diff.num_days();
 
-        if self.is_ignored(secret).await? || self.is_ignored_by_pattern(secret).await? {
-            // return ignored result
-            result.state = ValidatorState::Ignored;
-            return Ok(result);
-        }
-
-        let mut retention_days = self.config.default_rotation_days;
+        let mut policy = RotationPolicy::Default(self.config.default_rotation_days);
 
         // Check if custom retention time is set per secret
         let re = Regex::new(r"^[A-Z0-9\_]+\_R(\d{1,4})$").unwrap();
         let captures = re.captures(secret.name.as_str());
         if let Some(retention) = captures {
             // Unwrap is safe due to the regex pattern matching only decimals
-            retention_days = retention.get(1).unwrap().as_str().parse::<i64>().unwrap();
+            policy = RotationPolicy::Suffix(retention.get(1).unwrap().as_str().parse::<i64>().unwrap());
         }
 
-        if diff.num_days() >= retention_days {
-            result.state = ValidatorState::Expired;
-            result.days_overdue = diff.num_days() - retention_days;
+        let exemption = if self.is_ignored(secret).await? {
+            Some(Exemption::IgnoreList)
+        } else if self.is_ignored_by_pattern(secret).await? {
+            Some(Exemption::IgnorePattern(self.config.ignore_pattern.clone().unwrap_or_default()))
+        } else {
+            None
+        };
+
+        if exemption.is_some() {
+            // return ignored result
+            result.state = ValidatorState::Ignored;
         } else {
-            result.days_left = retention_days - diff.num_days();
-            if result.days_left <= self.config.expiration_notice_days {
-                result.state = ValidatorState::ExpiresSoon;
+            let retention_days = policy.days();
+            if diff.num_days() >= retention_days {
+                result.state = ValidatorState::Expired;
+                result.days_overdue = diff.num_days() - retention_days;
+            } else {
+                result.days_left = retention_days - diff.num_days();
+                if result.days_left <= self.config.expiration_notice_days {
+                    result.state = ValidatorState::ExpiresSoon;
+                }
             }
         }
+        result.severity = Severity::new(result.state, result.exposure);
+        Ok(ValidatorExplanation {
+            result,
+            policy,
+            exemption,
+            notice_days: self.config.expiration_notice_days,
+        })
+    }
+
+    /// Validates a credential that expires on its own at `expires_at`. The rotation
+    /// period does not apply, it only has to be replaced before it expires.
+    pub async fn validate_expiring(&self, secret: &GitHubAPISecret, expires_at: DateTime<Utc>) -> Result<ValidatorResult, Error> {
+        let mut result = self.validate_secret(secret).await?;
+        if result.state == ValidatorState::Ignored {
+            return Ok(result);
+        }
+        let days_left = expires_at.signed_duration_since(chrono::Utc::now()).num_days();
+        result.days_left = days_left.max(0);
+        result.days_overdue = 0;
+        result.state = if days_left <= self.config.expiration_notice_days {
+            ValidatorState::ExpiresSoon
+        } else {
+            ValidatorState::NotExpired
+        };
+        result.severity = Severity::new(result.state, result.exposure);
         Ok(result)
     }
 
     async fn is_ignored_by_pattern(&self, secret: &GitHubAPISecret) -> Result<bool, Error> {
         if let Some(ref ignore_pattern) = self.config.ignore_pattern {
-            let re = Regex::new(ignore_pattern).unwrap();
+            let re = Regex::new(ignore_pattern)
+                .with_context(|| format!("Invalid ignore pattern '{}'", ignore_pattern))?;
             Ok(re.is_match(secret.name.as_str()))
         } else {
             Ok(false)
@@ -93,7 +208,6 @@ impl Validator {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::prelude::*;
     use chrono::Duration;
     use crate::config::ObserverConfig;
     use crate::github_api::GitHubAPISecret;
@@ -105,12 +219,14 @@ mod tests {
             expiration_notice_days: 5,
             ignore_secrets: None,
             ignore_pattern: None,
+            ..Default::default()
         };
         let validator = Validator::new(config);
         let secret = GitHubAPISecret {
             name: String::from("TEST_SECRET"),
             created_at: Utc::now() - Duration::days(100),
             updated_at: Utc::now() - Duration::days(91),
+            ..Default::default()
         };
 
         let result = validator.validate_secret(&secret).await.unwrap();
@@ -125,12 +241,14 @@ mod tests {
             expiration_notice_days: 5,
             ignore_secrets: None,
             ignore_pattern: None,
+            ..Default::default()
         };
         let validator = Validator::new(config);
         let secret = GitHubAPISecret {
             name: String::from("TEST_SECRET_R5"),
             created_at: Utc::now() - Duration::days(100),
             updated_at: Utc::now() - Duration::days(10),
+            ..Default::default()
         };
 
         let result = validator.validate_secret(&secret).await.unwrap();
@@ -145,12 +263,14 @@ mod tests {
             expiration_notice_days: 1,
             ignore_secrets: None,
             ignore_pattern: None,
+            ..Default::default()
         };
         let validator = Validator::new(config);
         let secret = GitHubAPISecret {
             name: String::from("TEST_SECRET"),
             created_at: Utc::now() - Duration::days(90),
             updated_at: Utc::now() - Duration::days(85),
+            ..Default::default()
         };
 
         let result = validator.validate_secret(&secret).await.unwrap();
@@ -165,12 +285,14 @@ mod tests {
             expiration_notice_days: 5,
             ignore_secrets: None,
             ignore_pattern: None,
+            ..Default::default()
         };
         let validator = Validator::new(config);
         let secret = GitHubAPISecret {
             name: String::from("TEST_SECRET_R1000"),
             created_at: Utc::now() - Duration::days(90),
             updated_at: Utc::now() - Duration::days(100),
+            ..Default::default()
         };
 
         let result = validator.validate_secret(&secret).await.unwrap();
@@ -185,12 +307,14 @@ mod tests {
             expiration_notice_days: 60,
             ignore_secrets: None,
             ignore_pattern: None,
+            ..Default::default()
         };
         let validator = Validator::new(config);
         let secret = GitHubAPISecret {
             name: String::from("TEST_SECRET"),
             created_at: Utc::now() - Duration::days(90),
             updated_at: Utc::now() - Duration::days(85),
+            ..Default::default()
         };
 
         let result = validator.validate_secret(&secret).await.unwrap();
@@ -205,12 +329,14 @@ mod tests {
             expiration_notice_days: 60,
             ignore_secrets: None,
             ignore_pattern: None,
+            ..Default::default()
         };
         let validator = Validator::new(config);
         let secret = GitHubAPISecret {
             name: String::from("TEST_SECRET_R100"),
             created_at: Utc::now() - Duration::days(100),
             updated_at: Utc::now() - Duration::days(90),
+            ..Default::default()
         };
 
         let result = validator.validate_secret(&secret).await.unwrap();
@@ -225,12 +351,14 @@ mod tests {
             expiration_notice_days: 5,
             ignore_secrets: Some(vec![String::from("TEST_SECRET")]),
             ignore_pattern: None,
+            ..Default::default()
         };
         let validator = Validator::new(config);
         let secret = GitHubAPISecret {
             name: String::from("TEST_SECRET"),
             created_at: Utc::now() - Duration::days(90),
             updated_at: Utc::now(),
+            ..Default::default()
         };
 
         let result = validator.validate_secret(&secret).await.unwrap();
@@ -244,15 +372,96 @@ mod tests {
             expiration_notice_days: 5,
             ignore_secrets: None,
             ignore_pattern: Some(r"^TEST_".to_string()),
+            ..Default::default()
         };
         let validator = Validator::new(config);
         let secret = GitHubAPISecret {
             name: String::from("TEST_SECRET"),
             created_at: Utc::now() - Duration::days(90),
             updated_at: Utc::now(),
+            ..Default::default()
         };
 
         let result = validator.validate_secret(&secret).await.unwrap();
         assert_eq!(result.state, ValidatorState::Ignored);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_validate_expiring() {
+        let validator = Validator::new(ObserverConfig {
+            default_rotation_days: 90,
+            expiration_notice_days: 5,
+            ..Default::default()
+        });
+        // Older than the rotation period, but it expires on its own.
+        let secret = GitHubAPISecret {
+            name: String::from("octocat/deploy"),
+            created_at: Utc::now() - Duration::days(200),
+            updated_at: Utc::now() - Duration::days(200),
+            ..Default::default()
+        };
+
+        let result = validator.validate_expiring(&secret, Utc::now() + Duration::days(30)).await.unwrap();
+        assert_eq!(result.state, ValidatorState::NotExpired);
+        assert_eq!(result.days_overdue, 0);
+        let result = validator.validate_expiring(&secret, Utc::now() + Duration::days(3)).await.unwrap();
+        assert_eq!(result.state, ValidatorState::ExpiresSoon);
+        assert_eq!(result.severity, Severity::Medium);
+    }
+
+    #[tokio::test]
+    async fn test_explain_secret() {
+        let config = ObserverConfig {
+            default_rotation_days: 90,
+            expiration_notice_days: 5,
+            ignore_secrets: None,
+            ignore_pattern: Some(r"^IGNORED_".to_string()),
+            ..Default::default()
+        };
+        let validator = Validator::new(config);
+        let secret = GitHubAPISecret {
+            name: String::from("TEST_SECRET_R30"),
+            created_at: Utc::now() - Duration::days(100),
+            updated_at: Utc::now() - Duration::days(40),
+            ..Default::default()
+        };
+        let explanation = validator.explain_secret(&secret).await.unwrap();
+        assert_eq!(explanation.policy, RotationPolicy::Suffix(30));
+        assert_eq!(explanation.exemption, None);
+        assert_eq!(explanation.result.state, ValidatorState::Expired);
+
+        let secret = GitHubAPISecret {
+            name: String::from("IGNORED_SECRET"),
+            created_at: Utc::now() - Duration::days(100),
+            updated_at: Utc::now() - Duration::days(100),
+            ..Default::default()
+        };
+        let explanation = validator.explain_secret(&secret).await.unwrap();
+        assert_eq!(explanation.policy, RotationPolicy::Default(90));
+        assert_eq!(explanation.exemption, Some(Exemption::IgnorePattern(r"^IGNORED_".to_string())));
+        assert_eq!(explanation.result.state, ValidatorState::Ignored);
+    }
+
+    #[tokio::test]
+    async fn test_severity_raised_by_exposure() {
+        let config = ObserverConfig {
+            default_rotation_days: 90,
+            expiration_notice_days: 5,
+            ignore_secrets: None,
+            ignore_pattern: None,
+            ..Default::default()
+        };
+        let validator = Validator::new(config);
+        let mut secret = GitHubAPISecret {
+            name: String::from("TEST_SECRET"),
+            created_at: Utc::now() - Duration::days(100),
+            updated_at: Utc::now() - Duration::days(91),
+            ..Default::default()
+        };
+        assert_eq!(validator.validate_secret(&secret).await.unwrap().severity, Severity::High);
+        secret.exposure = Exposure::ForkPullRequest;
+        assert_eq!(validator.validate_secret(&secret).await.unwrap().severity, Severity::Critical);
+        secret.updated_at = Utc::now();
+        assert_eq!(validator.validate_secret(&secret).await.unwrap().severity, Severity::Low);
+    }
+}
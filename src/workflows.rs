@@ -0,0 +1,278 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use anyhow::{Context, Error};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::github_api::GitHubAPI;
+
+const WORKFLOWS_PATH: &str = ".github/workflows";
+const ACTIONS_PATH: &str = ".github/actions";
+
+/// Secrets that are provided by GitHub and never have to be created.
+const BUILTIN_SECRETS: [&str; 1] = ["GITHUB_TOKEN"];
+
+/// Expressions in `actions/checkout` inputs that select the code of a pull request
+/// instead of the base branch.
+const UNTRUSTED_REFS: [&str; 4] = [
+    "github.event.pull_request.head",
+    "github.head_ref",
+    "github.event.workflow_run.head",
+    "refs/pull/",
+];
+
+/// Who can trigger a workflow that has access to a secret, ordered by risk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Exposure {
+    /// Only reachable by workflows that run on trusted events, e.g. `push`.
+    #[default]
+    Trusted,
+    /// Reachable by `issue_comment` workflows, which anyone can trigger with a comment.
+    IssueComment,
+    /// Reachable by `pull_request_target` or `workflow_run` workflows that check out
+    /// the code of pull requests from forks while having access to secrets.
+    ForkPullRequest,
+}
+
+impl Exposure {
+    /// Exposure of a trigger, where `untrusted_checkout` is set if the workflow checks
+    /// out the head of a pull request. Without it, `pull_request_target` and
+    /// `workflow_run` only run code of the base branch.
+    fn from_trigger(trigger: &str, untrusted_checkout: bool) -> Self {
+        match trigger {
+            "pull_request_target" | "workflow_run" if untrusted_checkout => Exposure::ForkPullRequest,
+            "issue_comment" => Exposure::IssueComment,
+            _ => Exposure::Trusted,
+        }
+    }
+}
+
+/// A workflow or composite action of a repository.
+#[derive(Debug, Clone)]
+pub struct WorkflowFile {
+    pub path: String,
+    pub content: String,
+}
+
+/// Fetches all workflows and composite actions of the default branch.
+pub async fn fetch_workflow_files(github_api: &GitHubAPI, repository: &str) -> Result<Vec<WorkflowFile>, Error> {
+    let mut paths = Vec::new();
+    for entry in github_api.get_directory(repository, WORKFLOWS_PATH).await? {
+        if entry.kind == "file" && (entry.name.ends_with(".yml") || entry.name.ends_with(".yaml")) {
+            paths.push(entry.path);
+        }
+    }
+    for action in github_api.get_directory(repository, ACTIONS_PATH).await? {
+        if action.kind != "dir" {
+            continue;
+        }
+        for entry in github_api.get_directory(repository, &action.path).await? {
+            if entry.kind == "file" && (entry.name == "action.yml" || entry.name == "action.yaml") {
+                paths.push(entry.path);
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    for path in paths {
+        if let Some(content) = github_api.get_file(repository, &path).await
+            .with_context(|| format!("Failed to read {}", path))? {
+            files.push(WorkflowFile { path, content });
+        }
+    }
+    Ok(files)
+}
+
+/// Secrets referenced by the workflows of a repository.
+#[derive(Debug, Default)]
+pub struct SecretReferences {
+    /// Upper case secret name to the paths of the referencing workflows.
+    references: BTreeMap<String, BTreeSet<String>>,
+    /// Secrets declared by reusable workflows, which are provided by the caller.
+    declared: HashSet<String>,
+    /// Set if a workflow passes all secrets to another workflow with `secrets: inherit`.
+    inherits: bool,
+    /// Highest exposure of the workflows referencing a secret.
+    exposures: BTreeMap<String, Exposure>,
+    /// Highest exposure of the workflows that pass all secrets on.
+    inherited_exposure: Exposure,
+}
+
+impl SecretReferences {
+    pub fn parse(files: &[WorkflowFile]) -> Self {
+        // Secret names are case insensitive and only contain alphanumeric characters or underscores.
+        let reference = Regex::new(r#"secrets\s*(?:\.\s*([A-Za-z_][A-Za-z0-9_]*)|\[\s*['"]([A-Za-z_][A-Za-z0-9_]*)['"]\s*\])"#).unwrap();
+        let inherit = Regex::new(r"(?m)^\s*secrets\s*:\s*inherit\s*$").unwrap();
+
+        let mut references = SecretReferences::default();
+        for file in files {
+            let workflow = match serde_yaml::from_str::<serde_yaml::Value>(&file.content) {
+                Ok(workflow) => workflow,
+                Err(e) => {
+                    debug!("Failed to parse {}. Reason: {}", file.path, e);
+                    serde_yaml::Value::Null
+                }
+            };
+            let exposure = workflow_exposure(&workflow);
+            for captures in reference.captures_iter(&file.content) {
+                if let Some(name) = captures.get(1).or_else(|| captures.get(2)) {
+                    let name = name.as_str().to_uppercase();
+                    let secret_exposure = references.exposures.entry(name.clone()).or_default();
+                    *secret_exposure = (*secret_exposure).max(exposure);
+                    references.references.entry(name).or_default().insert(file.path.clone());
+                }
+            }
+            if inherit.is_match(&file.content) {
+                references.inherits = true;
+                references.inherited_exposure = references.inherited_exposure.max(exposure);
+            }
+            references.declared.extend(declared_secrets(&workflow));
+        }
+        references
+    }
+
+    /// Paths of the workflows that reference the secret.
+    pub fn workflows(&self, secret: &str) -> Vec<String> {
+        self.references
+            .get(&secret.to_uppercase())
+            .map(|paths| paths.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether the secret may be used. With `secrets: inherit` any secret may be
+    /// used by a workflow outside of the repository.
+    pub fn is_used(&self, secret: &str) -> bool {
+        self.inherits || self.references.contains_key(&secret.to_uppercase())
+    }
+
+    /// Highest exposure of the workflows that can read the secret.
+    pub fn exposure(&self, secret: &str) -> Exposure {
+        let exposure = self.exposures.get(&secret.to_uppercase()).copied().unwrap_or_default();
+        exposure.max(self.inherited_exposure)
+    }
+
+    /// Referenced secrets that are neither available nor provided by GitHub or a caller.
+    pub fn missing<'a>(&'a self, available: &'a HashSet<String>) -> impl Iterator<Item = (&'a String, &'a BTreeSet<String>)> {
+        self.references.iter().filter(move |(name, _)| {
+            !available.contains(*name)
+                && !self.declared.contains(*name)
+                && !BUILTIN_SECRETS.contains(&name.as_str())
+        })
+    }
+}
+
+/// Highest exposure of the triggers in `on`, which can be a single event, a list or a map.
+fn workflow_exposure(workflow: &serde_yaml::Value) -> Exposure {
+    let triggers: Vec<&str> = match workflow.get("on") {
+        Some(serde_yaml::Value::String(trigger)) => vec![trigger.as_str()],
+        Some(serde_yaml::Value::Sequence(triggers)) => triggers.iter().filter_map(|t| t.as_str()).collect(),
+        Some(serde_yaml::Value::Mapping(triggers)) => triggers.keys().filter_map(|t| t.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    let untrusted_checkout = checks_out_untrusted_code(workflow);
+    triggers.into_iter().map(|trigger| Exposure::from_trigger(trigger, untrusted_checkout)).max().unwrap_or_default()
+}
+
+/// Whether a step of the workflow runs `actions/checkout` with the ref or repository
+/// of a pull request head.
+fn checks_out_untrusted_code(workflow: &serde_yaml::Value) -> bool {
+    let Some(jobs) = workflow.get("jobs").and_then(|jobs| jobs.as_mapping()) else {
+        return false;
+    };
+    jobs.values()
+        .filter_map(|job| job.get("steps").and_then(|steps| steps.as_sequence()))
+        .flatten()
+        .filter(|step| step.get("uses")
+            .and_then(|uses| uses.as_str())
+            .is_some_and(|uses| uses.split('@').next() == Some("actions/checkout")))
+        .filter_map(|step| step.get("with"))
+        .any(|with| ["ref", "repository"].iter().any(|input| with.get(input)
+            .and_then(|value| value.as_str())
+            .is_some_and(|value| UNTRUSTED_REFS.iter().any(|untrusted| value.contains(untrusted)))))
+}
+
+/// Secrets declared under `on.workflow_call.secrets` of a reusable workflow.
+fn declared_secrets(workflow: &serde_yaml::Value) -> Vec<String> {
+    workflow.get("on")
+        .and_then(|on| on.get("workflow_call"))
+        .and_then(|call| call.get("secrets"))
+        .and_then(|secrets| secrets.as_mapping())
+        .map(|secrets| secrets.keys().filter_map(|key| key.as_str()).map(str::to_uppercase).collect())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretUsage {
+    pub repository: String,
+    pub secret: String,
+    pub workflows: Vec<String>,
+}
+
+/// Secrets that are never referenced and references to secrets that don't exist.
+#[derive(Debug, Default, Serialize)]
+pub struct UsageReport {
+    pub unused: Vec<SecretUsage>,
+    pub missing: Vec<SecretUsage>,
+}
+
+impl UsageReport {
+    pub fn is_empty(&self) -> bool {
+        self.unused.is_empty() && self.missing.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, content: &str) -> WorkflowFile {
+        WorkflowFile { path: path.to_string(), content: content.to_string() }
+    }
+
+    #[test]
+    fn test_parse_references() {
+        let files = vec![
+            file(".github/workflows/ci.yml", "jobs:\n  build:\n    env:\n      TOKEN: ${{ secrets.npm_token }}\n      OTHER: ${{ secrets['OTHER_SECRET'] }}\n      GH: ${{ secrets.GITHUB_TOKEN }}\n"),
+            file(".github/workflows/deploy.yml", "jobs:\n  deploy:\n    env:\n      TOKEN: ${{ secrets.NPM_TOKEN }}\n"),
+        ];
+        let references = SecretReferences::parse(&files);
+        assert_eq!(references.workflows("NPM_TOKEN"), vec![".github/workflows/ci.yml", ".github/workflows/deploy.yml"]);
+        assert!(references.is_used("OTHER_SECRET"));
+        assert!(!references.is_used("UNUSED"));
+
+        let available = HashSet::from(["NPM_TOKEN".to_string()]);
+        let missing: Vec<_> = references.missing(&available).map(|(name, _)| name.as_str()).collect();
+        assert_eq!(missing, vec!["OTHER_SECRET"]);
+    }
+
+    #[test]
+    fn test_reusable_workflows() {
+        let files = vec![
+            file(".github/workflows/reusable.yml", "on:\n  workflow_call:\n    secrets:\n      deploy_key:\n        required: true\njobs:\n  deploy:\n    env:\n      KEY: ${{ secrets.deploy_key }}\n"),
+            file(".github/workflows/caller.yml", "jobs:\n  call:\n    uses: org/other/.github/workflows/deploy.yml@main\n    secrets: inherit\n"),
+        ];
+        let references = SecretReferences::parse(&files);
+        assert_eq!(references.missing(&HashSet::new()).count(), 0);
+        assert!(references.is_used("ANY_SECRET"));
+    }
+
+    #[test]
+    fn test_exposure() {
+        let files = vec![
+            file(".github/workflows/push.yml", "on: push\njobs:\n  a:\n    env:\n      A: ${{ secrets.TRUSTED }}\n      B: ${{ secrets.SHARED }}\n"),
+            file(".github/workflows/comment.yml", "on: [issue_comment]\njobs:\n  a:\n    env:\n      B: ${{ secrets.SHARED }}\n"),
+            file(".github/workflows/target.yml", "on:\n  pull_request_target:\n    types: [opened]\njobs:\n  a:\n    steps:\n      - uses: actions/checkout@v4\n        with:\n          ref: ${{ github.event.pull_request.head.sha }}\n      - env:\n          C: ${{ secrets.FORK }}\n"),
+            file(".github/workflows/label.yml", "on: pull_request_target\njobs:\n  a:\n    steps:\n      - uses: actions/checkout@v4\n      - env:\n          D: ${{ secrets.LABEL }}\n"),
+            file(".github/workflows/report.yml", "on: workflow_run\njobs:\n  a:\n    steps:\n      - uses: actions/checkout@v4\n        with:\n          ref: ${{ github.event.workflow_run.head_sha }}\n      - env:\n          E: ${{ secrets.REPORT }}\n"),
+        ];
+        let references = SecretReferences::parse(&files);
+        assert_eq!(references.exposure("TRUSTED"), Exposure::Trusted);
+        assert_eq!(references.exposure("SHARED"), Exposure::IssueComment);
+        assert_eq!(references.exposure("FORK"), Exposure::ForkPullRequest);
+        // Only code of the base branch runs, even though the trigger is reachable from forks.
+        assert_eq!(references.exposure("LABEL"), Exposure::Trusted);
+        assert_eq!(references.exposure("REPORT"), Exposure::ForkPullRequest);
+        assert_eq!(references.exposure("UNKNOWN"), Exposure::Trusted);
+    }
+}